@@ -0,0 +1,101 @@
+#![no_main]
+
+use std::collections::BTreeMap;
+
+use btree_one::BTree;
+use libfuzzer_sys::fuzz_target;
+
+// Every operation is read from 9 bytes: the kind, then the key and the value as little-endian u32s
+const OP_SIZE: usize = 9;
+
+#[derive(Debug)]
+enum Kind {
+    Insert,
+    Delete,
+    Search,
+    Range,
+    CheckInvariants,
+}
+
+#[derive(Debug)]
+struct Op {
+    kind: Kind,
+    key: u32,
+    value: u32,
+}
+
+impl Op {
+    fn parse(bytes: &[u8]) -> Op {
+        let kind = match bytes[0] % 5 {
+            0 => Kind::Insert,
+            1 => Kind::Delete,
+            2 => Kind::Search,
+            3 => Kind::Range,
+            _ => Kind::CheckInvariants,
+        };
+        let key = u32::from_le_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]);
+        let value = u32::from_le_bytes([bytes[5], bytes[6], bytes[7], bytes[8]]);
+
+        Op { kind, key, value }
+    }
+}
+
+// Checks the shape of the tree and compares its entries against the model
+fn check_invariants(tree: &BTree<u32, u32>, model: &BTreeMap<u32, u32>, t: usize) {
+    assert_eq!(tree.len(), model.len());
+    assert!(tree.is_balanced());
+    tree.validate_sizes().unwrap();
+    tree.assert_key_order();
+
+    for (depth, level) in tree.level_iter().enumerate() {
+        for node in level {
+            assert!(node.n <= 2 * t - 1);
+            assert!(depth == 0 || node.n >= t - 1);
+        }
+    }
+
+    let expected: Vec<(u32, u32)> = model.iter().map(|(&k, &v)| (k, v)).collect();
+    tree.verify_against_slice(&expected).unwrap();
+    assert_eq!(tree.min(), model.keys().next());
+    assert_eq!(tree.max(), model.keys().next_back());
+}
+
+fuzz_target!(|data: &[u8]| {
+    let t = 2;
+    let mut tree: BTree<u32, u32> = BTree::new(t);
+    let mut model = BTreeMap::new();
+
+    for bytes in data.chunks_exact(OP_SIZE) {
+        let op = Op::parse(bytes);
+        match op.kind {
+            Kind::Insert => {
+                // insert keeps duplicate keys, so replace the value of a key that's already there
+                match tree.get_mut(&op.key) {
+                    Some(v) => *v = op.value,
+                    None => tree.insert(op.key, op.value),
+                }
+                model.insert(op.key, op.value);
+            }
+            Kind::Delete => {
+                let removed = tree.remove(&op.key).map(|e| *e.value());
+                assert_eq!(removed, model.remove(&op.key));
+            }
+            Kind::Search => {
+                assert_eq!(tree.get(&op.key), model.get(&op.key));
+            }
+            Kind::Range => {
+                let (lo, hi) = (op.key.min(op.value), op.key.max(op.value));
+                let found: Vec<u32> = tree.range(lo..=hi).map(|e| *e.get_key()).collect();
+                let expected: Vec<u32> = model.range(lo..=hi).map(|(&k, _)| k).collect();
+                assert_eq!(found, expected);
+                assert_eq!(tree.keys_in_range_count(lo..=hi), expected.len());
+            }
+            Kind::CheckInvariants => check_invariants(&tree, &model, t),
+        }
+
+        // Checked after every operation, so a failure points at the operation that broke the tree
+        check_invariants(&tree, &model, t);
+    }
+
+    check_invariants(&tree, &model, t);
+});
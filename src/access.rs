@@ -0,0 +1,110 @@
+use std::collections::BinaryHeap;
+
+use crate::BTree;
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Returns the n keys lookups hit most often, with their hit counts, most accessed first.
+    /// Every lookup that finds its key counts, e.g. `get`, `get_mut` or `search`.
+    /// Ties go to the larger key. The hits of a key are forgotten once it's removed from the tree.
+    pub fn hot_keys(&self, n: usize) -> Vec<(K, u64)> {
+        let root = match &self.root {
+            Some(r) => r,
+            None => return Vec::new(),
+        };
+
+        let mut heap: BinaryHeap<(u64, K)> = self
+            .ctx
+            .hits
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(&k, &count)| (count, k))
+            .collect();
+
+        let mut hot = Vec::with_capacity(n.min(heap.len()));
+        while hot.len() < n {
+            let (count, key) = match heap.pop() {
+                Some(top) => top,
+                None => break,
+            };
+            if root.find(&key).is_some() {
+                hot.push((key, count));
+            }
+        }
+        hot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BTree, SearchHint};
+
+    #[test]
+    fn every_lookup_counts_as_a_hit() {
+        let mut tree = BTree::new(2);
+        for i in 0..100u32 {
+            tree.insert(i, i);
+        }
+
+        tree.get(&1);
+        *tree.get_mut(&2).unwrap() += 1;
+        tree.search(&3);
+        tree.search_linear(&4);
+        tree.search_budgeted(&5, 100).unwrap();
+        tree.lookup_with_hint(&6, &SearchHint::default());
+        tree.compare_and_swap(&7, &7, 8).unwrap();
+        tree.get_or_try_insert_with(8, || Ok::<u32, ()>(0)).unwrap();
+        for _ in 0..3 {
+            tree.get(&50);
+        }
+
+        // Misses don't count
+        tree.get(&1000);
+        tree.search(&1000);
+
+        let hot = tree.hot_keys(100);
+        assert_eq!(hot[0], (50, 3));
+        let mut once: Vec<u32> = hot[1..]
+            .iter()
+            .map(|&(k, count)| {
+                assert_eq!(count, 1);
+                k
+            })
+            .collect();
+        once.sort();
+        assert_eq!(once, (1..=8).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn removed_keys_lose_their_hits() {
+        let mut tree = BTree::new(3);
+        for round in 0..20u32 {
+            for i in round * 100..(round + 1) * 100 {
+                tree.insert(i, i);
+                tree.get(&i);
+            }
+
+            // Churn through removal paths, one key at a time and in bulk
+            let base = round * 100;
+            tree.remove(&base);
+            tree.remove_lazy(&(base + 1));
+            tree.clear_range(base + 10..base + 30);
+            tree.take_range(base + 30..base + 50);
+            tree.remove_keys(&[base + 50, base + 51]);
+            tree.apply_delta(&[(base + 60, None)]);
+            tree.retain_range(base + 70..base + 90, |_, _| false);
+
+            let tracked: Vec<u32> = tree.ctx.hits.lock().unwrap().keys().copied().collect();
+            assert!(tree.contains_all(&tracked));
+            assert!(tracked.len() <= tree.len());
+        }
+
+        tree.drain_into(|_, _| {});
+        assert!(tree.ctx.hits.lock().unwrap().is_empty());
+        assert!(tree.hot_keys(10).is_empty());
+    }
+}
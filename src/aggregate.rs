@@ -0,0 +1,119 @@
+use std::collections::VecDeque;
+use std::ops::{Add, RangeBounds};
+
+use crate::BTree;
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Sums the values of every key up to and including key.
+    /// Nodes don't carry subtree sums, so this walks the entries in O(n).
+    pub fn prefix_sum_at(&self, key: &K) -> P
+    where
+        P: Add<Output = P> + Default,
+    {
+        self.range(..=key)
+            .fold(P::default(), |sum, e| sum + e.value)
+    }
+
+    /// Folds the entries in key order with a fallible function, stopping at the first error
+    pub fn try_fold<B, E, F>(&self, init: B, mut f: F) -> Result<B, E>
+    where
+        F: FnMut(B, &K, &P) -> Result<B, E>,
+    {
+        let mut acc = init;
+        for e in self.iter() {
+            acc = f(acc, &e.key, &e.value)?;
+        }
+        Ok(acc)
+    }
+
+    /// Folds the values of the entries in range in key order, e.g. to roll up a window of a time series.
+    /// Seeks straight to the start of the range and stops at its end, without collecting it.
+    pub fn reduce_range<R, B, F>(&self, range: R, init: B, mut f: F) -> B
+    where
+        R: RangeBounds<K>,
+        F: FnMut(B, &P) -> B,
+    {
+        self.range(range).fold(init, |acc, e| f(acc, &e.value))
+    }
+
+    /// Returns the largest value of every window of `window` consecutive entries in key order,
+    /// `len - window + 1` windows in all, or none if the tree holds fewer entries than a window.
+    /// Keeps a deque of the values that can still become a window maximum, so it runs in O(n).
+    pub fn sliding_window_max(&self, window: usize) -> Vec<Option<P>>
+    where
+        P: Ord,
+    {
+        if window == 0 {
+            panic!("Window size has to be at least 1");
+        }
+
+        let mut maxima = Vec::with_capacity((self.len() + 1).saturating_sub(window));
+        // Positions and values in decreasing order of value, the front is the maximum of the current window
+        let mut candidates: VecDeque<(usize, P)> = VecDeque::new();
+        for (i, e) in self.iter().enumerate() {
+            while candidates.back().is_some_and(|&(_, v)| v <= e.value) {
+                candidates.pop_back();
+            }
+            candidates.push_back((i, e.value));
+
+            if candidates.front().is_some_and(|&(j, _)| j + window <= i) {
+                candidates.pop_front();
+            }
+            if i + 1 >= window {
+                maxima.push(candidates.front().map(|&(_, v)| v));
+            }
+        }
+        maxima
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BTree;
+
+    #[test]
+    fn try_fold_stops_at_the_first_error() {
+        let mut tree = BTree::new(2);
+        for i in 0..100u32 {
+            // Two entries fail the check, only the first one is reported
+            let value = if i == 40 || i == 70 { 1000 } else { i };
+            tree.insert(i, value);
+        }
+
+        let mut calls = 0;
+        let result = tree.try_fold(0, |sum, k, v| {
+            calls += 1;
+            if *v > 500 {
+                Err(*k)
+            } else {
+                Ok(sum + v)
+            }
+        });
+
+        // The first 40 entries, the one that failed, and nothing after it
+        assert_eq!(result, Err(40));
+        assert_eq!(calls, 41);
+
+        let sum: Result<u32, u32> = tree.try_fold(0, |sum, _, v| Ok(sum + v));
+        assert_eq!(sum, Ok((0..100).sum::<u32>() - 110 + 2000));
+    }
+
+    #[test]
+    fn reduce_range_sums_a_window() {
+        let mut tree = BTree::new(3);
+        for i in 0..1000u64 {
+            tree.insert(i, i * i);
+        }
+
+        let sum = tree.reduce_range(100..200, 0, |acc, v| acc + v);
+        assert_eq!(sum, (100..200u64).map(|i| i * i).sum::<u64>());
+
+        let count = tree.reduce_range(990.., 0, |acc, _| acc + 1);
+        assert_eq!(count, 10);
+        assert_eq!(tree.reduce_range(2000..3000, 7, |acc, v| acc + v), 7);
+    }
+}
@@ -0,0 +1,310 @@
+use std::ops::{Bound, RangeBounds};
+
+use crate::{after_end, before_start};
+
+// Nodes live in an arena and refer to each other by index, which lets every leaf link to the next one
+#[derive(Debug)]
+enum BPlusNode<K, P> {
+    // children[i] holds the keys below keys[i], the last child the keys from the last separator up
+    Internal {
+        keys: Vec<K>,
+        children: Vec<usize>,
+    },
+    Leaf {
+        keys: Vec<K>,
+        values: Vec<P>,
+        next: Option<usize>,
+    },
+}
+
+/// B+-tree variant of `BTree`, where every entry lives in a leaf, internal nodes only hold separator keys,
+/// and every leaf links to the next one, so range scans walk along the leaves without going back up.
+/// Keys are unique, inserting an existing key replaces its value. Removal isn't supported yet.
+#[derive(Debug)]
+pub struct BPlusTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    t: usize,
+    nodes: Vec<BPlusNode<K, P>>,
+    root: usize,
+    len: usize,
+}
+
+impl<K, P> BPlusTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    pub fn new(t: usize) -> BPlusTree<K, P> {
+        if t < 2 {
+            panic!("Degree may not be smaller than 2");
+        }
+
+        BPlusTree {
+            t,
+            nodes: vec![BPlusNode::Leaf {
+                keys: Vec::new(),
+                values: Vec::new(),
+                next: None,
+            }],
+            root: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, key: &K) -> Option<&P> {
+        let leaf = self.find_leaf(key);
+        match &self.nodes[leaf] {
+            BPlusNode::Leaf { keys, values, .. } => {
+                let i = keys.binary_search(key).ok()?;
+                Some(&values[i])
+            }
+            BPlusNode::Internal { .. } => unreachable!(),
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: P) {
+        // A split of the root grows the tree by a level
+        if let Some((separator, right)) = self.insert_into(self.root, key, value) {
+            self.nodes.push(BPlusNode::Internal {
+                keys: vec![separator],
+                children: vec![self.root, right],
+            });
+            self.root = self.nodes.len() - 1;
+        }
+    }
+
+    /// Iterates over the entries in range in key order.
+    /// Only the first leaf is found by descending, the scan then follows the links between the leaves.
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> BPlusRange<'_, K, P, R> {
+        let leaf = match range.start_bound() {
+            Bound::Included(s) | Bound::Excluded(s) => self.find_leaf(s),
+            Bound::Unbounded => self.first_leaf(),
+        };
+
+        let pos = match &self.nodes[leaf] {
+            BPlusNode::Leaf { keys, .. } => {
+                keys.iter().take_while(|k| before_start(&range, k)).count()
+            }
+            BPlusNode::Internal { .. } => unreachable!(),
+        };
+
+        BPlusRange {
+            tree: self,
+            leaf: Some(leaf),
+            pos,
+            range,
+        }
+    }
+
+    // Index of the leaf that holds key, or would hold it
+    fn find_leaf(&self, key: &K) -> usize {
+        let mut node = self.root;
+        while let BPlusNode::Internal { keys, children } = &self.nodes[node] {
+            // Keys equal to a separator are on its right
+            let i = keys.iter().take_while(|s| *s <= key).count();
+            node = children[i];
+        }
+        node
+    }
+
+    fn first_leaf(&self) -> usize {
+        let mut node = self.root;
+        while let BPlusNode::Internal { children, .. } = &self.nodes[node] {
+            node = children[0];
+        }
+        node
+    }
+
+    // Inserts into the subtree of node, returning the separator and the new right sibling if node had to split
+    fn insert_into(&mut self, node: usize, key: K, value: P) -> Option<(K, usize)> {
+        let max = 2 * self.t - 1;
+        let next_index = self.nodes.len();
+
+        let (separator, right) = match &mut self.nodes[node] {
+            BPlusNode::Leaf { keys, values, next } => {
+                match keys.binary_search(&key) {
+                    Ok(i) => {
+                        values[i] = value;
+                        return None;
+                    }
+                    Err(i) => {
+                        keys.insert(i, key);
+                        values.insert(i, value);
+                        self.len += 1;
+                    }
+                }
+
+                if keys.len() <= max {
+                    return None;
+                }
+
+                // The right half moves to a new leaf, which slots into the chain after this one,
+                // and its first key is copied up as the separator
+                let right_keys = keys.split_off(keys.len() / 2);
+                let right_values = values.split_off(values.len() / 2);
+                let separator = right_keys[0];
+                let right = BPlusNode::Leaf {
+                    keys: right_keys,
+                    values: right_values,
+                    next: next.replace(next_index),
+                };
+                (separator, right)
+            }
+            BPlusNode::Internal { keys, children } => {
+                let i = keys.iter().take_while(|s| **s <= key).count();
+                let child = children[i];
+                let (separator, right) = self.insert_into(child, key, value)?;
+
+                let (keys, children) = match &mut self.nodes[node] {
+                    BPlusNode::Internal { keys, children } => (keys, children),
+                    BPlusNode::Leaf { .. } => unreachable!(),
+                };
+                keys.insert(i, separator);
+                children.insert(i + 1, right);
+
+                if keys.len() <= max {
+                    return None;
+                }
+
+                // The middle separator moves up, unlike in a leaf it isn't kept
+                let mid = keys.len() / 2;
+                let right_keys = keys.split_off(mid + 1);
+                let separator = keys.pop().unwrap();
+                let right_children = children.split_off(mid + 1);
+                let right = BPlusNode::Internal {
+                    keys: right_keys,
+                    children: right_children,
+                };
+
+                // The recursive insert may have added nodes, so the new one goes at the end of the arena
+                self.nodes.push(right);
+                return Some((separator, self.nodes.len() - 1));
+            }
+        };
+
+        self.nodes.push(right);
+        Some((separator, next_index))
+    }
+}
+
+/// Iterator over a key range of a `BPlusTree`, following the links between the leaves
+pub struct BPlusRange<'a, K, P, R>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    tree: &'a BPlusTree<K, P>,
+    leaf: Option<usize>,
+    pos: usize,
+    range: R,
+}
+
+impl<'a, K, P, R> Iterator for BPlusRange<'a, K, P, R>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+    R: RangeBounds<K>,
+{
+    type Item = (&'a K, &'a P);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (keys, values, next) = match &self.tree.nodes[self.leaf?] {
+                BPlusNode::Leaf { keys, values, next } => (keys, values, next),
+                BPlusNode::Internal { .. } => unreachable!(),
+            };
+
+            if self.pos < keys.len() {
+                let key = &keys[self.pos];
+                if after_end(&self.range, key) {
+                    self.leaf = None;
+                    return None;
+                }
+
+                self.pos += 1;
+                return Some((key, &values[self.pos - 1]));
+            }
+
+            self.leaf = *next;
+            self.pos = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{BPlusNode, BPlusTree};
+    use crate::tests::lcg;
+
+    #[test]
+    fn inserts_and_lookups_match_a_map() {
+        let mut tree = BPlusTree::new(3);
+        let mut model = BTreeMap::new();
+        let mut seed = 181;
+        for _ in 0..5000 {
+            let key = lcg(&mut seed) % 2000;
+            let value = lcg(&mut seed);
+            tree.insert(key, value);
+            model.insert(key, value);
+        }
+
+        assert_eq!(tree.len(), model.len());
+        for key in 0..2100 {
+            assert_eq!(tree.get(&key), model.get(&key));
+        }
+        assert!(tree.range(..).eq(model.iter()));
+    }
+
+    #[test]
+    fn range_scan_walks_only_the_leaves() {
+        let mut tree = BPlusTree::new(3);
+        let mut seed = 1810;
+        let mut keys: Vec<u32> = (0..10_000).collect();
+        for i in (1..keys.len()).rev() {
+            keys.swap(i, lcg(&mut seed) as usize % (i + 1));
+        }
+        for &k in keys.iter() {
+            tree.insert(k, k * 2);
+        }
+
+        // Leaves holding keys in the range, the scan may also look at the first key of the one after them
+        let spanned = tree
+            .nodes
+            .iter()
+            .filter(|node| match node {
+                BPlusNode::Leaf { keys, .. } => keys.iter().any(|k| (1000..2000).contains(k)),
+                BPlusNode::Internal { .. } => false,
+            })
+            .count();
+
+        let mut range = tree.range(1000..2000);
+        let mut visited = vec![range.leaf.unwrap()];
+        let mut scanned = Vec::new();
+        while let Some((&k, &v)) = range.next() {
+            assert_eq!(v, k * 2);
+            scanned.push(k);
+            if let Some(leaf) = range.leaf.filter(|l| visited.last() != Some(l)) {
+                visited.push(leaf);
+            }
+        }
+
+        assert_eq!(scanned, (1000..2000).collect::<Vec<u32>>());
+        assert!(visited
+            .iter()
+            .all(|&i| matches!(tree.nodes[i], BPlusNode::Leaf { .. })));
+        assert!(visited.len() == spanned || visited.len() == spanned + 1);
+    }
+}
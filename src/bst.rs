@@ -0,0 +1,94 @@
+use crate::{BTree, Entry, Node};
+
+/// A node of a plain, unbalanced binary search tree.
+/// Every key in the left subtree is smaller than the key and every key in the right subtree is larger.
+#[derive(Debug, Clone)]
+pub struct BSTNode<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    pub key: K,
+    pub value: P,
+    pub left: Option<Box<BSTNode<K, P>>>,
+    pub right: Option<Box<BSTNode<K, P>>>,
+}
+
+impl<K, P> BSTNode<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    pub fn new(key: K, value: P) -> BSTNode<K, P> {
+        BSTNode {
+            key,
+            value,
+            left: None,
+            right: None,
+        }
+    }
+
+    /// Inserts below this node without any rebalancing, replacing the value if the key is already present
+    pub fn insert(&mut self, key: K, value: P) {
+        let mut node = self;
+        loop {
+            let next = if key < node.key {
+                &mut node.left
+            } else if key > node.key {
+                &mut node.right
+            } else {
+                node.value = value;
+                return;
+            };
+
+            match next {
+                Some(n) => node = n,
+                None => {
+                    *next = Some(Box::new(BSTNode::new(key, value)));
+                    return;
+                }
+            }
+        }
+    }
+
+    // Collects the entries in key order. Unbalanced trees can be as deep as they are large,
+    // so this keeps its own stack instead of recursing.
+    fn in_order(&self) -> Vec<Entry<K, P>> {
+        let mut entries = Vec::new();
+        let mut stack = Vec::new();
+        let mut node = Some(self);
+        while node.is_some() || !stack.is_empty() {
+            while let Some(n) = node {
+                stack.push(n);
+                node = n.left.as_deref();
+            }
+
+            let n = stack.pop().unwrap();
+            entries.push(Entry::new(n.key, n.value));
+            node = n.right.as_deref();
+        }
+        entries
+    }
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Converts a binary search tree into a B-tree of degree t.
+    /// An in-order walk of a binary search tree yields its keys sorted, which is exactly what a bulk load takes,
+    /// so this runs in O(n) however unbalanced the binary search tree is.
+    pub fn from_binary_search_tree(t: usize, bst: &BSTNode<K, P>) -> BTree<K, P> {
+        let entries = bst.in_order();
+        debug_assert!(
+            entries.windows(2).all(|w| w[0].key < w[1].key),
+            "Binary search tree is not ordered"
+        );
+
+        let mut tree = BTree::new(t);
+        tree.root = Node::load_sorted(t, entries, 2 * t - 1);
+        tree.refresh_extremes();
+        tree
+    }
+}
@@ -0,0 +1,1153 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, VecDeque};
+use std::ops::{RangeBounds, RangeFull};
+
+use crate::context::AtomicStats;
+use crate::join::join;
+use crate::{after_end, before_start, BTree, BTreeError, DuplicateKeyError, Entry, Node};
+
+// Smallest number of entries a non-root subtree of the given height may hold
+fn min_entries(t: usize, height: usize) -> usize {
+    t.saturating_pow(height as u32) - 1
+}
+
+// Largest number of entries a subtree of the given height may hold
+fn max_entries(t: usize, height: usize) -> usize {
+    (2 * t).saturating_pow(height as u32) - 1
+}
+
+// Number of keys each node is packed with for the given fill factor
+fn keys_per_node(t: usize, fill_factor: f64) -> usize {
+    if !(0.5..=1.0).contains(&fill_factor) {
+        panic!("Fill factor has to be between 0.5 and 1.0");
+    }
+
+    let per_node = (fill_factor * (2 * t - 1) as f64).round() as usize;
+    per_node.max(t - 1)
+}
+
+// Entries held by a subtree of the given height when every node holds `per_node` keys
+fn packed_entries(per_node: usize, height: usize) -> usize {
+    (per_node + 1).saturating_pow(height as u32) - 1
+}
+
+// Splits total items into groups of at most max, filling every group but the last two,
+// which share the rest so the last one holds at least min. A single group may hold fewer.
+fn packed_groups(total: usize, max: usize, min: usize) -> Vec<usize> {
+    let count = total.div_ceil(max);
+    if count <= 1 {
+        return vec![total];
+    }
+
+    let rest = total - (count - 2) * max;
+    let second_last = max.min(rest - min);
+    let mut groups = vec![max; count - 2];
+    groups.push(second_last);
+    groups.push(rest - second_last);
+    groups
+}
+
+impl<K, P> Node<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    // Builds a subtree of exactly `height` levels out of the next `n` entries of `entries`,
+    // aiming for `per_node` keys in every node
+    fn from_sorted<I>(
+        t: usize,
+        entries: &mut I,
+        n: usize,
+        height: usize,
+        per_node: usize,
+        root: bool,
+    ) -> Node<K, P>
+    where
+        I: Iterator<Item = Entry<K, P>>,
+    {
+        let mut node = Node::new(t, height == 1);
+        node.size = n;
+
+        if node.leaf {
+            for i in 0..n {
+                node.keys[i] = entries.next();
+            }
+            node.n = n;
+            return node;
+        }
+
+        // Aim for the amount of children the target packing asks for,
+        // but stay within what keeps every child a valid subtree
+        let child_min = min_entries(t, height - 1) + 1;
+        let child_max = max_entries(t, height - 1) + 1;
+        let wanted = (n + 1 + packed_entries(per_node, height - 1))
+            / (packed_entries(per_node, height - 1) + 1);
+        let lowest = std::cmp::max(if root { 2 } else { t }, (n + child_max) / child_max);
+        let highest = std::cmp::min(2 * t, (n + 1) / child_min);
+        let c = wanted.max(lowest).min(highest);
+
+        // Spread the entries evenly, every child but the last is followed by a separator
+        let base = (n + 1) / c;
+        let rem = (n + 1) % c;
+        for i in 0..c {
+            let size = if i < rem { base } else { base - 1 };
+            node.child[i] = Some(Node::from_sorted(
+                t,
+                entries,
+                size,
+                height - 1,
+                per_node,
+                false,
+            ));
+
+            if i < c - 1 {
+                node.keys[i] = entries.next();
+            }
+        }
+        node.n = c - 1;
+
+        node
+    }
+
+    // Hands every entry of this subtree to sink in key order, consuming it.
+    // Each child is dropped as soon as it's been drained, so its memory is freed before moving on.
+    fn drain_into<S: FnMut(K, P)>(mut self, sink: &mut S) {
+        for i in 0..=self.n {
+            if !self.leaf {
+                self.child[i].take().unwrap().drain_into(sink);
+            }
+
+            if i < self.n {
+                let (key, value) = self.keys[i].take().unwrap().into_pair();
+                sink(key, value);
+            }
+        }
+    }
+
+    // Bulk-loads a tree bottom-up with every node holding 2t - 1 keys, except the last two on every level
+    pub(crate) fn load_packed(t: usize, entries: Vec<Entry<K, P>>) -> Option<Node<K, P>> {
+        if entries.is_empty() {
+            return None;
+        }
+
+        // Every leaf but the last is followed by a separator, so a leaf and its separator take up 2t entries
+        let mut entries = entries.into_iter();
+        let groups = packed_groups(entries.len() + 1, 2 * t, t);
+        let mut nodes = Vec::with_capacity(groups.len());
+        let mut separators = Vec::with_capacity(groups.len());
+        for (i, &g) in groups.iter().enumerate() {
+            let mut leaf = Node::new(t, true);
+            for j in 0..g - 1 {
+                leaf.keys[j] = entries.next();
+            }
+            leaf.n = g - 1;
+            leaf.size = g - 1;
+            nodes.push(leaf);
+
+            if i < groups.len() - 1 {
+                separators.push(entries.next().unwrap());
+            }
+        }
+
+        // Every level groups up to 2t nodes of the one below, with the separators between them
+        while nodes.len() > 1 {
+            let groups = packed_groups(nodes.len(), 2 * t, t);
+            let mut below = nodes.into_iter();
+            let mut below_separators = separators.into_iter();
+            nodes = Vec::with_capacity(groups.len());
+            separators = Vec::with_capacity(groups.len());
+
+            for (i, &g) in groups.iter().enumerate() {
+                let mut parent = Node::new(t, false);
+                for j in 0..g {
+                    let child = below.next().unwrap();
+                    parent.size += child.size;
+                    parent.child[j] = Some(child);
+
+                    if j < g - 1 {
+                        parent.keys[j] = below_separators.next();
+                    }
+                }
+                parent.n = g - 1;
+                parent.size += g - 1;
+                nodes.push(parent);
+
+                if i < groups.len() - 1 {
+                    separators.push(below_separators.next().unwrap());
+                }
+            }
+        }
+
+        nodes.pop()
+    }
+
+    // Bulk-loads a whole tree out of sorted, deduplicated entries
+    pub(crate) fn load_sorted(
+        t: usize,
+        entries: Vec<Entry<K, P>>,
+        per_node: usize,
+    ) -> Option<Node<K, P>> {
+        let n = entries.len();
+        if n == 0 {
+            return None;
+        }
+
+        // Find the lowest tree that fits every entry at the target packing
+        let mut height = 1;
+        while packed_entries(per_node, height) < n {
+            height += 1;
+        }
+
+        // The root needs at least two children that are each valid subtrees
+        while height > 1 && n + 1 < 2 * (min_entries(t, height - 1) + 1) {
+            height -= 1;
+        }
+
+        Some(Node::from_sorted(
+            t,
+            &mut entries.into_iter(),
+            n,
+            height,
+            per_node,
+            true,
+        ))
+    }
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Builds a tree in O(n) out of entries that are sorted by key in ascending order, without duplicates.
+    /// Every node is packed as full as possible.
+    pub fn from_sorted_iter<I>(t: usize, iter: I) -> BTree<K, P>
+    where
+        I: IntoIterator<Item = (K, P)>,
+    {
+        BTree::from_sorted_iter_with_fill(t, iter, 1.0)
+    }
+
+    /// Like `from_sorted_iter`, but packs every node to `fill_factor` of its `2t - 1` key capacity.
+    ///
+    /// A fill factor of 1.0 gives the fewest nodes and the lowest tree, which is best for read-mostly workloads,
+    /// but the first insert into any full leaf has to split it.
+    /// Lower fill factors (e.g. 0.7) spend more memory and possibly a level of height
+    /// on leaving room for inserts, so they can land without immediately splitting.
+    pub fn from_sorted_iter_with_fill<I>(t: usize, iter: I, fill_factor: f64) -> BTree<K, P>
+    where
+        I: IntoIterator<Item = (K, P)>,
+    {
+        let mut tree = BTree::new(t);
+
+        let entries: Vec<_> = iter.into_iter().map(|(k, v)| Entry::new(k, v)).collect();
+        debug_assert!(
+            entries.windows(2).all(|w| w[0].key < w[1].key),
+            "Entries are not sorted"
+        );
+
+        tree.root = Node::load_sorted(t, entries, keys_per_node(t, fill_factor));
+        tree.refresh_extremes();
+
+        tree
+    }
+
+    /// Builds a tree out of several iterators that are each sorted by key, like the merge step of an external sort.
+    /// The iterators are merged through a min-heap and the result is bulk-loaded.
+    /// If several iterators hold the same key, the entry from the earliest iterator is kept.
+    pub fn from_merged_iters<I>(t: usize, iters: Vec<I>) -> BTree<K, P>
+    where
+        I: Iterator<Item = (K, P)>,
+    {
+        let mut iters = iters;
+
+        // The heap holds the next key of every iterator that isn't exhausted, ties go to the earlier iterator
+        let mut heads: Vec<Option<P>> = Vec::with_capacity(iters.len());
+        let mut heap = BinaryHeap::with_capacity(iters.len());
+        for (i, iter) in iters.iter_mut().enumerate() {
+            let next = iter.next();
+            if let Some((k, _)) = next {
+                heap.push(Reverse((k, i)));
+            }
+            heads.push(next.map(|(_, v)| v));
+        }
+
+        let mut entries: Vec<Entry<K, P>> = Vec::new();
+        while let Some(Reverse((k, i))) = heap.pop() {
+            let value = heads[i].take().unwrap();
+            if entries.last().is_none_or(|e| e.key != k) {
+                entries.push(Entry::new(k, value));
+            }
+
+            if let Some((next, v)) = iters[i].next() {
+                debug_assert!(k <= next, "Iterator {} is not sorted", i);
+                heap.push(Reverse((next, i)));
+                heads[i] = Some(v);
+            }
+        }
+
+        let mut tree = BTree::new(t);
+        tree.root = Node::load_sorted(t, entries, 2 * t - 1);
+        tree.refresh_extremes();
+        tree
+    }
+
+    /// Applies a batch of mutations, sorted by key, in a single merge pass over the tree.
+    /// `Some(v)` upserts the key with `v`, `None` removes it. If a key occurs several times in delta, the last one wins.
+    /// A key the tree holds several entries for ends up with a single entry holding `v`, or none at all.
+    pub fn apply_delta(&mut self, delta: &[(K, Option<P>)]) {
+        debug_assert!(
+            delta.windows(2).all(|w| w[0].0 <= w[1].0),
+            "Delta is not sorted"
+        );
+
+        let current = self.sorted_entries();
+
+        let mut merged = Vec::with_capacity(current.len() + delta.len());
+        let mut i = 0;
+        let mut j = 0;
+        while i < current.len() || j < delta.len() {
+            if j == delta.len() || (i < current.len() && current[i].key < delta[j].0) {
+                merged.push(current[i]);
+                i += 1;
+                continue;
+            }
+
+            // Skip to the last mutation of this key
+            let (key, mut value) = delta[j];
+            while j + 1 < delta.len() && delta[j + 1].0 == key {
+                j += 1;
+                value = delta[j].1;
+            }
+            j += 1;
+
+            // The mutation replaces every current entry with the key
+            while i < current.len() && current[i].key == key {
+                i += 1;
+            }
+
+            if let Some(v) = value {
+                merged.push(Entry::new(key, v));
+            }
+        }
+
+        self.root = Node::load_sorted(self.t, merged, 2 * self.t - 1);
+        self.refresh_extremes();
+        self.forget_removed_hits();
+    }
+
+    /// Removes a batch of keys, sorted in ascending order, in a single merge pass over the tree.
+    /// Returns the number of entries actually removed.
+    pub fn remove_keys(&mut self, keys: &[K]) -> usize {
+        debug_assert!(keys.windows(2).all(|w| w[0] <= w[1]), "Keys are not sorted");
+
+        let current = self.sorted_entries();
+        let mut kept = Vec::with_capacity(current.len());
+        let mut j = 0;
+        for e in current.iter() {
+            while j < keys.len() && keys[j] < e.key {
+                j += 1;
+            }
+            if j == keys.len() || keys[j] != e.key {
+                kept.push(*e);
+            }
+        }
+
+        let removed = current.len() - kept.len();
+        if removed > 0 {
+            self.root = Node::load_sorted(self.t, kept, 2 * self.t - 1);
+            self.refresh_extremes();
+            self.forget_removed_hits();
+        }
+        removed
+    }
+
+    /// Upserts a batch of keys, where f decides the new value of every key from its current value, if any.
+    /// f returning `None` leaves the key unchanged. A key that occurs several times is passed to f once per
+    /// occurrence, seeing the value the previous call produced.
+    /// A batch that is small next to the tree costs a descent per key. A large one is sorted and read in one pass
+    /// over the tree, and the changes are written in another, so it costs O(n + m log m) instead.
+    /// Either way, a key the tree holds several entries for ends up with a single entry if f changes it.
+    pub fn upsert_many<F>(&mut self, keys: &[K], mut f: F)
+    where
+        F: FnMut(&K, Option<&P>) -> Option<P>,
+    {
+        let mut sorted = keys.to_vec();
+        sorted.sort();
+
+        let height = self.root.as_ref().map_or(0, |r| r.height());
+        if sorted.len() * height < self.len() {
+            self.upsert_each(&sorted, f);
+            return;
+        }
+
+        let mut changes = Vec::new();
+        let mut entries = self.iter().peekable();
+        let mut i = 0;
+        while i < sorted.len() {
+            let key = sorted[i];
+            while entries.next_if(|e| e.key < key).is_some() {}
+            let mut value = entries.peek().filter(|e| e.key == key).map(|e| e.value);
+
+            let mut changed = false;
+            while i < sorted.len() && sorted[i] == key {
+                if let Some(v) = f(&key, value.as_ref()) {
+                    value = Some(v);
+                    changed = true;
+                }
+                i += 1;
+            }
+
+            if changed {
+                changes.push((key, value));
+            }
+        }
+
+        if !changes.is_empty() {
+            self.apply_delta(&changes);
+        }
+    }
+
+    // Upserts the sorted keys one run of equal keys at a time, descending once or twice per run
+    fn upsert_each<F>(&mut self, sorted: &[K], mut f: F)
+    where
+        F: FnMut(&K, Option<&P>) -> Option<P>,
+    {
+        for run in sorted.chunk_by(|a, b| a == b) {
+            let key = run[0];
+            let mut value = self.range(key..=key).next().map(|e| e.value);
+
+            let mut changed = false;
+            for _ in run {
+                if let Some(v) = f(&key, value.as_ref()) {
+                    value = Some(v);
+                    changed = true;
+                }
+            }
+
+            if let (true, Some(v)) = (changed, value) {
+                // Collapse duplicates to a single entry, as the merge pass does
+                while self.range(key..=key).nth(1).is_some() {
+                    self.remove(&key);
+                }
+                match self.get_mut(&key) {
+                    Some(slot) => *slot = v,
+                    None => self.insert(key, v),
+                }
+            }
+        }
+    }
+
+    /// Merges other into this tree in a single pass over both, keeping the degree of this tree.
+    /// For keys in both trees, `policy(ours, theirs)` decides the merged value,
+    /// e.g. last-write-wins with `|_, b| b`, or a sum with `|a, b| a + b`.
+    pub fn merge_with_policy<F>(self, other: BTree<K, P>, policy: F) -> BTree<K, P>
+    where
+        F: Fn(P, P) -> P,
+    {
+        let ours = self.sorted_entries();
+        let theirs = other.sorted_entries();
+
+        let mut merged = Vec::with_capacity(ours.len() + theirs.len());
+        let mut i = 0;
+        let mut j = 0;
+        while i < ours.len() || j < theirs.len() {
+            if j == theirs.len() || (i < ours.len() && ours[i].key < theirs[j].key) {
+                merged.push(ours[i]);
+                i += 1;
+            } else if i == ours.len() || theirs[j].key < ours[i].key {
+                merged.push(theirs[j]);
+                j += 1;
+            } else {
+                let value = policy(ours[i].value, theirs[j].value);
+                merged.push(Entry::new(ours[i].key, value));
+                i += 1;
+                j += 1;
+            }
+        }
+
+        let mut tree = self;
+        tree.root = Node::load_sorted(tree.t, merged, 2 * tree.t - 1);
+        tree.refresh_extremes();
+        tree
+    }
+
+    /// Removes every entry in range. The tree is split at both ends of the range, the part in between
+    /// is dropped whole without visiting the subtrees inside it, and the two outer parts are joined again.
+    /// Only the nodes along the paths to the two ends are taken apart and rebalanced, so besides freeing
+    /// the cleared nodes this takes O(t log n) however large the range is.
+    pub fn clear_range<R: RangeBounds<K>>(&mut self, range: R) {
+        if self.range_count(&range) == 0 {
+            return;
+        }
+
+        let root = self.root.take().unwrap();
+        let height = root.height();
+        let ctx = &mut self.ctx;
+        let (left, rest) = root.split_by(height, &|k| before_start(&range, k), ctx);
+        let right = match rest {
+            (Some(r), h) => r.split_by(h, &|k| !after_end(&range, k), ctx).1,
+            empty => empty,
+        };
+
+        // The first entry of the right part joins the two back together
+        self.root = match right {
+            (Some(mut r), h) => {
+                let separator = r.pop_first(ctx);
+                let right = if r.n > 0 {
+                    (Some(r), h)
+                } else {
+                    (r.child[0].take(), h - 1)
+                };
+                join(self.t, left, separator, right, ctx).0
+            }
+            _ => left.0,
+        };
+        self.refresh_extremes();
+        self.forget_removed_hits();
+    }
+
+    /// Removes every entry and hands it to sink in key order, e.g. to stream the tree to disk.
+    /// Unlike collecting the entries first, nodes are freed as soon as they've been emptied,
+    /// so memory use goes down as the drain goes on.
+    pub fn drain_into<S: FnMut(K, P)>(&mut self, mut sink: S) {
+        if let Some(r) = self.root.take() {
+            r.drain_into(&mut sink);
+        }
+        self.refresh_extremes();
+        self.forget_removed_hits();
+    }
+
+    /// Inserts every pair with `try_insert`, or none of them: if one fails the tree is restored
+    /// to how it was before the batch and the error is returned. Besides the entries that covers the stats,
+    /// the events not yet handed to an observer, the lookup hits and the nodes kept for reuse.
+    /// Split observers have already been called for the splits that were undone.
+    /// The root is cloned up front to restore it from, so a batch costs O(n) on top of the inserts.
+    pub fn insert_transaction(&mut self, pairs: Vec<(K, P)>) -> Result<(), BTreeError> {
+        let snapshot = (self.root.clone(), self.min_key, self.max_key);
+        let stats = self.ctx.stats.as_ref().map(AtomicStats::load);
+        let events = self.ctx.events.as_ref().map(Vec::len);
+        let pooled = self.ctx.pool.len();
+        #[cfg(feature = "access_tracking")]
+        let hits = self.ctx.hits.lock().unwrap().clone();
+
+        for (key, value) in pairs {
+            if let Err(e) = self.try_insert(key, value) {
+                let (root, min_key, max_key) = snapshot;
+                if let Some(r) = std::mem::replace(&mut self.root, root) {
+                    self.ctx.pool.give_tree(r);
+                }
+                self.ctx.pool.truncate(pooled);
+                (self.min_key, self.max_key) = (min_key, max_key);
+
+                if let (Some(counters), Some(stats)) = (&self.ctx.stats, stats) {
+                    counters.store(stats);
+                }
+                if let (Some(list), Some(len)) = (&mut self.ctx.events, events) {
+                    list.truncate(len);
+                }
+                #[cfg(feature = "access_tracking")]
+                {
+                    *self.ctx.hits.get_mut().unwrap() = hits;
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes every entry in range and returns how many were removed.
+    /// The count takes O(log n) through the subtree sizes. A few entries are then removed one at a time,
+    /// in O(k log n) for k entries, otherwise the range is cut out whole by `clear_range`.
+    pub fn range_delete<R: RangeBounds<K>>(&mut self, range: R) -> usize {
+        let deleted = self.range_count(&range);
+        let height = self.level_iter().count();
+        if deleted * height < self.len() - deleted {
+            let keys: Vec<K> = self.range(range).map(|e| e.key).collect();
+            for key in keys.iter() {
+                self.remove(key);
+            }
+        } else {
+            self.clear_range(range);
+        }
+        deleted
+    }
+
+    /// Builds a new tree, with the same degree, holding a copy of the entries in range.
+    /// The range is read in order and bulk-loaded, this tree is left untouched.
+    pub fn subtree<R: RangeBounds<K>>(&self, range: R) -> BTree<K, P> {
+        let entries: Vec<_> = self.range(range).copied().collect();
+
+        let mut tree = BTree::new(self.t);
+        tree.root = Node::load_sorted(self.t, entries, 2 * self.t - 1);
+        tree.refresh_extremes();
+        tree
+    }
+
+    /// Splits the entries into one tree per bucket, keyed by what bucket_fn returns for their key.
+    /// The entries are read in key order, so each bucket receives its entries sorted and is bulk-loaded.
+    /// Runs of consecutive keys in the same bucket, e.g. for a monotonic bucket_fn, are moved in one go.
+    pub fn bucket_by<G, F>(&self, bucket_fn: F) -> BTreeMap<G, BTree<K, P>>
+    where
+        G: Ord,
+        F: Fn(&K) -> G,
+    {
+        let mut buckets: BTreeMap<G, Vec<Entry<K, P>>> = BTreeMap::new();
+        let mut run: Vec<Entry<K, P>> = Vec::new();
+        let mut run_bucket = None;
+        for e in self.iter() {
+            let g = bucket_fn(&e.key);
+            if run_bucket.as_ref() != Some(&g) {
+                if let Some(b) = run_bucket.take() {
+                    buckets.entry(b).or_default().append(&mut run);
+                }
+                run_bucket = Some(g);
+            }
+            run.push(*e);
+        }
+        if let Some(b) = run_bucket {
+            buckets.entry(b).or_default().append(&mut run);
+        }
+
+        buckets
+            .into_iter()
+            .map(|(g, entries)| {
+                let mut tree = BTree::new(self.t);
+                tree.root = Node::load_sorted(self.t, entries, 2 * self.t - 1);
+                tree.refresh_extremes();
+                (g, tree)
+            })
+            .collect()
+    }
+
+    /// Removes every entry in range and returns them as a new tree with the same degree.
+    /// The tree is split at both ends of the range like in `clear_range`, the part in between becomes
+    /// the new tree and the two outer parts are joined again, so this takes O(t log n) however large the range is.
+    pub fn take_range<R: RangeBounds<K>>(&mut self, range: R) -> BTree<K, P> {
+        let mut tree = BTree::new(self.t);
+        if self.range_count(&range) == 0 {
+            return tree;
+        }
+
+        let root = self.root.take().unwrap();
+        let height = root.height();
+        let ctx = &mut self.ctx;
+        let (left, rest) = root.split_by(height, &|k| before_start(&range, k), ctx);
+        let (taken, right) = match rest {
+            (Some(r), h) => r.split_by(h, &|k| !after_end(&range, k), ctx),
+            empty => (empty, (None, 0)),
+        };
+
+        self.root = match right {
+            (Some(mut r), h) => {
+                let separator = r.pop_first(ctx);
+                let right = if r.n > 0 {
+                    (Some(r), h)
+                } else {
+                    (r.child[0].take(), h - 1)
+                };
+                join(self.t, left, separator, right, ctx).0
+            }
+            _ => left.0,
+        };
+        self.refresh_extremes();
+        self.forget_removed_hits();
+
+        tree.root = taken.0;
+        tree.refresh_extremes();
+        tree
+    }
+
+    /// Rebuilds the tree with every node packed to `fill_factor`, see `from_sorted_iter_with_fill`
+    pub fn compact(&mut self, fill_factor: f64) {
+        let per_node = keys_per_node(self.t, fill_factor);
+        self.root = Node::load_sorted(self.t, self.sorted_entries(), per_node);
+    }
+
+    /// Rebuilds the tree with every node packed as tightly as possible, leaving the fewest nodes.
+    /// Useful after many removals have left nodes close to their minimum of `t - 1` keys.
+    pub fn shrink_to_fit(&mut self) {
+        self.compact(1.0);
+    }
+
+    /// Rebuilds the tree at the same degree with every node holding `2t - 1` keys,
+    /// except the right-most two on every level, which share the rest. Leaves the fewest empty slots,
+    /// e.g. before a read-only phase, but the next inserts into a packed node all have to split it.
+    pub fn pack(&mut self) {
+        self.root = Node::load_packed(self.t, self.sorted_entries());
+    }
+
+    /// Builds a new tree with every key transformed by f, e.g. to migrate to another key type.
+    /// f doesn't have to preserve the key order, but it has to keep the keys unique.
+    pub fn map_keys<K2, F>(&self, f: F) -> Result<BTree<K2, P>, DuplicateKeyError<K2>>
+    where
+        K2: Eq + Ord + Copy,
+        F: Fn(K) -> K2,
+    {
+        let mut entries: Vec<_> = self.iter().map(|e| Entry::new(f(e.key), e.value)).collect();
+        entries.sort_by_key(|e| e.key);
+
+        if let Some(w) = entries.windows(2).find(|w| w[0].key == w[1].key) {
+            return Err(DuplicateKeyError { key: w[0].key });
+        }
+
+        let mut tree = BTree::new(self.t);
+        tree.root = Node::load_sorted(self.t, entries, 2 * self.t - 1);
+        tree.refresh_extremes();
+        Ok(tree)
+    }
+
+    /// Returns the entries in an order that keeps splits down when inserted into an empty tree of the same degree.
+    /// The order is the level-order traversal of the sorted entries seen as a balanced binary tree:
+    /// the median first, then the medians of both halves, and so on. Spreading the inserts evenly lets
+    /// the leaves fill up before they split, unlike sorted inserts which leave every split node half empty.
+    /// This is a heuristic for exploring how B-trees behave, it doesn't guarantee the fewest possible splits.
+    pub fn topological_sort_inserts(&self) -> Vec<(K, P)> {
+        let entries = self.sorted_entries();
+        let mut order = Vec::with_capacity(entries.len());
+
+        let mut halves = VecDeque::new();
+        halves.push_back(0..entries.len());
+        while let Some(range) = halves.pop_front() {
+            if range.is_empty() {
+                continue;
+            }
+
+            let mid = range.start + range.len() / 2;
+            order.push(entries[mid].into_pair());
+            halves.push_back(range.start..mid);
+            halves.push_back(mid + 1..range.end);
+        }
+        order
+    }
+
+    // Collects every entry in key order
+    pub(crate) fn sorted_entries(&self) -> Vec<Entry<K, P>> {
+        let mut entries = Vec::with_capacity(self.len());
+        if let Some(r) = &self.root {
+            r.walk_range(&RangeFull, &mut |e| entries.push(*e));
+        }
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::tests::{assert_valid, keys, lcg};
+    use crate::BTree;
+
+    #[test]
+    fn apply_delta_matches_individual_operations() {
+        let mut seed = 9;
+        let mut tree = BTree::new(3);
+        let mut model = BTreeMap::new();
+        for _ in 0..50 {
+            let mut delta: Vec<(u64, Option<u64>)> = (0..100)
+                .map(|_| {
+                    let key = lcg(&mut seed) % 300;
+                    let value = Some(lcg(&mut seed)).filter(|v| v % 2 == 0);
+                    (key, value)
+                })
+                .collect();
+            delta.sort_by_key(|d| d.0);
+
+            for &(key, value) in delta.iter() {
+                match value {
+                    Some(v) => model.insert(key, v),
+                    None => model.remove(&key),
+                };
+            }
+            tree.apply_delta(&delta);
+
+            assert_valid(&tree);
+            let expected: Vec<(u64, u64)> = model.iter().map(|(&k, &v)| (k, v)).collect();
+            assert_eq!(tree.verify_against_slice(&expected), Ok(()));
+        }
+    }
+
+    #[test]
+    fn apply_delta_replaces_every_duplicate() {
+        let mut tree = BTree::new(2);
+        for i in 0..20u32 {
+            tree.insert(i % 5, i);
+        }
+
+        tree.apply_delta(&[(1, Some(100)), (3, None)]);
+
+        assert_eq!(keys(&tree), [0, 0, 0, 0, 1, 2, 2, 2, 2, 4, 4, 4, 4]);
+        assert_eq!(tree.equal_range(&1), (4, 5));
+        assert_eq!(tree.get(&1), Some(&100));
+    }
+
+    #[test]
+    #[should_panic(expected = "Delta is not sorted")]
+    fn apply_delta_rejects_unsorted_delta() {
+        let mut tree: BTree<u32, u32> = BTree::new(2);
+        tree.apply_delta(&[(2, Some(2)), (1, Some(1))]);
+    }
+
+    #[test]
+    fn remove_keys_removes_the_present_ones() {
+        let mut tree = BTree::new(3);
+        for i in 0..1000u32 {
+            tree.insert(i, i);
+        }
+
+        // Every third key, some of them past the end of the tree
+        let batch: Vec<u32> = (0..1200).step_by(3).collect();
+        assert_eq!(tree.remove_keys(&batch), 334);
+
+        assert_valid(&tree);
+        assert_eq!(
+            keys(&tree),
+            (0..1000).filter(|k| k % 3 != 0).collect::<Vec<u32>>()
+        );
+        assert_eq!(tree.remove_keys(&batch), 0);
+        assert_eq!(tree.remove_keys(&[]), 0);
+        assert_eq!(tree.len(), 666);
+    }
+
+    #[test]
+    fn upsert_many_increments_or_inserts_defaults() {
+        let mut tree = BTree::new(2);
+        for i in 0..50u32 {
+            tree.insert(i * 2, 10);
+        }
+
+        // Unsorted, with 7 twice, and 4 skipped by f
+        let batch = [7, 3, 0, 98, 7, 4, 150, 40];
+        tree.upsert_many(&batch, |k, v| match v {
+            _ if *k == 4 => None,
+            Some(v) => Some(v + 1),
+            None => Some(0),
+        });
+
+        assert_valid(&tree);
+        assert_eq!(tree.len(), 53);
+        assert_eq!(tree.get(&0), Some(&11));
+        assert_eq!(tree.get(&98), Some(&11));
+        assert_eq!(tree.get(&40), Some(&11));
+        assert_eq!(tree.get(&4), Some(&10));
+        assert_eq!(tree.get(&3), Some(&0));
+        assert_eq!(tree.get(&150), Some(&0));
+        // Inserted by its first occurrence, then incremented by the second
+        assert_eq!(tree.get(&7), Some(&1));
+    }
+
+    #[test]
+    fn upsert_many_descends_per_key_for_small_batches() {
+        let mut seed = 7;
+        let mut tree = BTree::new(3);
+        for i in 0..2000u32 {
+            tree.insert(i * 2, i);
+        }
+        let mut model: BTreeMap<u32, u32> = tree.iter().map(|e| (e.key, e.value)).collect();
+
+        let f = |k: &u32, v: Option<&u32>| match v {
+            _ if k % 5 == 0 => None,
+            Some(v) => Some(v + 1),
+            None => Some(*k),
+        };
+        // The small batches are upserted key by key, the last one is merged in a single pass
+        for batch_len in [1, 10, 100, 3000] {
+            let batch: Vec<u32> = (0..batch_len)
+                .map(|_| (lcg(&mut seed) % 5000) as u32)
+                .collect();
+            tree.upsert_many(&batch, f);
+            let mut sorted = batch.clone();
+            sorted.sort();
+            for k in sorted {
+                if let Some(v) = f(&k, model.get(&k)) {
+                    model.insert(k, v);
+                }
+            }
+
+            assert_valid(&tree);
+            let entries: Vec<(u32, u32)> = tree.iter().map(|e| (e.key, e.value)).collect();
+            assert_eq!(
+                entries,
+                model.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>()
+            );
+        }
+
+        // Duplicates of a changed key collapse to one entry, as they do in the merge pass
+        tree.insert(4, 100);
+        tree.insert(4, 200);
+        tree.upsert_many(&[4], |_, v| v.map(|v| v + 1));
+        assert_eq!(tree.range(4..=4).count(), 1);
+        assert_eq!(tree.get(&4), Some(&(model[&4] + 1)));
+    }
+
+    #[test]
+    fn clear_range_keeps_the_complement() {
+        let mut tree = BTree::new(3);
+        for i in 0..1000u32 {
+            tree.insert(i, i * 2);
+        }
+
+        tree.clear_range(250..750);
+
+        assert_valid(&tree);
+        assert_eq!(keys(&tree), (0..250).chain(750..1000).collect::<Vec<u32>>());
+        assert!(tree.iter().all(|e| e.value == e.key * 2));
+        assert_eq!((tree.min(), tree.max()), (Some(&0), Some(&999)));
+    }
+
+    #[test]
+    fn clear_range_matches_a_model() {
+        use std::ops::Bound::{self, Excluded, Included, Unbounded};
+        use std::ops::RangeBounds;
+
+        let mut seed = 177;
+        for t in 2..6 {
+            for _ in 0..200 {
+                let len = lcg(&mut seed) % 400;
+                let mut tree = BTree::new(t);
+                for i in 0..len as u32 {
+                    tree.insert(i, i);
+                }
+
+                let bound = |seed: &mut u64| {
+                    let k = (lcg(seed) % (len + 20)) as u32;
+                    match lcg(seed) % 3 {
+                        0 => Included(k),
+                        1 => Excluded(k),
+                        _ => Unbounded,
+                    }
+                };
+                let range: (Bound<u32>, Bound<u32>) = (bound(&mut seed), bound(&mut seed));
+                let expected: Vec<u32> = (0..len as u32).filter(|k| !range.contains(k)).collect();
+
+                tree.clear_range(range);
+
+                assert_valid(&tree);
+                assert_eq!(
+                    keys(&tree),
+                    expected,
+                    "t = {}, len = {}, range {:?}",
+                    t,
+                    len,
+                    range
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn drain_into_hands_over_every_entry_in_order() {
+        let mut tree = BTree::new(4);
+        let mut seed = 186;
+        for _ in 0..100_000 {
+            let key = lcg(&mut seed) as u32;
+            tree.insert(key, key ^ 1);
+        }
+        let expected: Vec<u32> = keys(&tree);
+
+        let mut seen = Vec::new();
+        tree.drain_into(|k, v| {
+            assert_eq!(v, k ^ 1);
+            seen.push(k);
+        });
+
+        assert_eq!(seen.len(), 100_000);
+        assert_eq!(seen, expected);
+        assert!(tree.is_empty());
+        assert_eq!((tree.min(), tree.max()), (None, None));
+    }
+
+    #[test]
+    fn pack_leaves_few_empty_slots() {
+        let mut tree = BTree::new(4);
+        for i in 0..10_000u32 {
+            tree.insert(i, i + 1);
+        }
+        // Ascending inserts leave the nodes about half full
+        assert!(tree.capacity() > tree.len() / 2);
+
+        tree.pack();
+
+        assert_valid(&tree);
+        assert_eq!(keys(&tree), (0..10_000).collect::<Vec<u32>>());
+        assert!(tree.iter().all(|e| e.value == e.key + 1));
+
+        // Only the two right-most nodes of every level may have empty slots
+        let height = tree.level_iter().count();
+        assert!(tree.capacity() <= 2 * height * (2 * 4 - 1));
+        assert!(tree.capacity() * 100 < tree.len());
+    }
+
+    #[test]
+    fn insert_transaction_rolls_back_the_whole_tree() {
+        let mut tree = BTree::with_node_recycler(2);
+        tree.ctx.stats = Some(Default::default());
+        tree.ctx.events = Some(Vec::new());
+        for i in 0..200u32 {
+            tree.insert(i, i);
+        }
+        for i in 100..150 {
+            tree.remove(&i);
+        }
+        for i in (0..200).step_by(3) {
+            tree.get(&i);
+        }
+        tree.set_max_len(Some(170));
+
+        let keys_before = keys(&tree);
+        let stats = tree.op_stats();
+        let events = tree.ctx.events.clone();
+        let pooled = tree.ctx.pool.len();
+        assert!(pooled > 0);
+        #[cfg(feature = "access_tracking")]
+        let hits = tree.ctx.hits.lock().unwrap().clone();
+
+        // The batch gets as far as the 21st new key, splitting and drawing from the pool on the way
+        let batch: Vec<(u32, u32)> = (1000..1030).map(|i| (i, i)).collect();
+        assert_eq!(
+            tree.insert_transaction(batch),
+            Err(crate::BTreeError::CapacityExceeded { limit: 170 })
+        );
+
+        assert_valid(&tree);
+        assert_eq!(keys(&tree), keys_before);
+        assert_eq!(tree.op_stats(), stats);
+        assert_eq!(tree.ctx.events, events);
+        assert_eq!(tree.ctx.pool.len(), pooled);
+        #[cfg(feature = "access_tracking")]
+        assert_eq!(*tree.ctx.hits.lock().unwrap(), hits);
+
+        assert_eq!(tree.insert_transaction(vec![(1000, 0), (1001, 0)]), Ok(()));
+        assert_eq!(tree.len(), 152);
+    }
+
+    #[test]
+    fn insert_transaction_merges_existing_keys_at_the_limit() {
+        let mut tree = BTree::with_merge_fn(3, |old, new| old + new);
+        for i in 0..10u32 {
+            tree.insert(i, 1);
+        }
+        tree.set_max_len(Some(10));
+
+        // Merging into keys that are already there doesn't grow the tree
+        assert_eq!(
+            tree.insert_transaction(vec![(3, 1), (3, 1), (7, 5)]),
+            Ok(())
+        );
+        assert_eq!(tree.get(&3), Some(&3));
+        assert_eq!(tree.get(&7), Some(&6));
+
+        // A new key doesn't fit, and the merges before it are undone
+        assert!(tree.insert_transaction(vec![(3, 1), (10, 1)]).is_err());
+        assert_eq!(tree.get(&3), Some(&3));
+        assert_eq!(tree.len(), 10);
+    }
+
+    #[test]
+    fn take_range_splits_off_the_range() {
+        let mut tree = BTree::new(3);
+        for i in 0..1000u32 {
+            tree.insert(i, i * 2);
+        }
+
+        let taken = tree.take_range(250..=600);
+
+        assert_valid(&tree);
+        assert_valid(&taken);
+        assert_eq!(keys(&taken), (250..=600).collect::<Vec<u32>>());
+        assert!(taken.iter().all(|e| e.value == e.key * 2));
+        assert_eq!(keys(&tree), (0..250).chain(601..1000).collect::<Vec<u32>>());
+
+        // Ranges with nothing in them take nothing
+        assert!(tree.take_range(300..400).is_empty());
+        assert_eq!(tree.len(), 649);
+
+        let rest = tree.take_range(..);
+        assert!(tree.is_empty());
+        assert_eq!(rest.len(), 649);
+    }
+
+    #[test]
+    fn take_range_matches_a_model() {
+        use std::ops::Bound::{self, Excluded, Included, Unbounded};
+        use std::ops::RangeBounds;
+
+        let mut seed = 181;
+        for t in 2..6 {
+            for _ in 0..200 {
+                let len = lcg(&mut seed) % 400;
+                let mut tree = BTree::new(t);
+                for i in 0..len as u32 {
+                    tree.insert(i, i);
+                }
+
+                let bound = |seed: &mut u64| {
+                    let k = (lcg(seed) % (len + 20)) as u32;
+                    match lcg(seed) % 3 {
+                        0 => Included(k),
+                        1 => Excluded(k),
+                        _ => Unbounded,
+                    }
+                };
+                let range: (Bound<u32>, Bound<u32>) = (bound(&mut seed), bound(&mut seed));
+                let (expected_taken, expected_kept): (Vec<u32>, Vec<u32>) =
+                    (0..len as u32).partition(|k| range.contains(k));
+
+                let mut taken = tree.take_range(range);
+
+                assert_valid(&tree);
+                assert_valid(&taken);
+                assert_eq!(
+                    keys(&tree),
+                    expected_kept,
+                    "t = {}, len = {}, range {:?}",
+                    t,
+                    len,
+                    range
+                );
+                assert_eq!(
+                    keys(&taken),
+                    expected_taken,
+                    "t = {}, len = {}, range {:?}",
+                    t,
+                    len,
+                    range
+                );
+                assert_eq!(taken.min(), expected_taken.first());
+                assert_eq!(tree.max(), expected_kept.last());
+
+                // Both halves are trees in their own right
+                taken.insert(1000, 0);
+                tree.insert(1000, 0);
+                assert_valid(&taken);
+                assert_valid(&tree);
+            }
+        }
+    }
+
+    #[test]
+    fn subtree_copies_the_range() {
+        let mut tree = BTree::new(3);
+        for i in 0..1000u32 {
+            tree.insert(i, i + 1);
+        }
+
+        let sub = tree.subtree(200..300);
+        assert_valid(&sub);
+        assert_eq!(sub.len(), 100);
+        assert_eq!(tree.len(), 1000);
+
+        // traverse visits the nodes top-down, so compare it in key order
+        let mut traversed: Vec<(u32, u32)> = sub
+            .traverse()
+            .unwrap()
+            .iter()
+            .map(|e| (e.key, e.value))
+            .collect();
+        traversed.sort();
+        let expected: Vec<(u32, u32)> = tree.range(200..300).map(|e| (e.key, e.value)).collect();
+        assert_eq!(traversed, expected);
+
+        assert!(tree.subtree(2000..).is_empty());
+    }
+}
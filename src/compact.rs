@@ -0,0 +1,286 @@
+use crate::{BTree, DecodeError, Entry, FixedSized, Node};
+
+// Layout of encode_compact, integers little endian:
+//
+//   t (u8), entry count (u32), then every node in pre-order
+//
+// Every node record has the same size for a given t, so record i starts at a known offset:
+//
+//   leaf (u8, 0 or 1), n (u16), 2t - 1 (key, value) slots, unused slots zeroed
+const HEADER_SIZE: usize = 5;
+
+fn record_size<K: FixedSized, P: FixedSized>(t: usize) -> usize {
+    3 + (2 * t - 1) * (K::SIZE + P::SIZE)
+}
+
+// Most levels a tree of degree t with count entries can have. A root h levels high
+// holds at least 2t^(h - 1) - 1 entries, as all nodes below it hold at least t - 1.
+fn max_height(t: usize, count: usize) -> usize {
+    let mut height = 1;
+    let mut power = t;
+    while power.saturating_mul(2) - 1 <= count {
+        height += 1;
+        power = power.saturating_mul(t);
+    }
+    height
+}
+
+impl<K, P> Node<K, P>
+where
+    K: Eq + Ord + Copy + FixedSized,
+    P: Copy + FixedSized,
+{
+    fn encode_compact(&self, buf: &mut Vec<u8>) {
+        let start = buf.len();
+        buf.resize(start + record_size::<K, P>(self.t), 0);
+
+        let record = &mut buf[start..];
+        record[0] = self.leaf as u8;
+        (self.n as u16).encode(&mut record[1..3]);
+        let slot = K::SIZE + P::SIZE;
+        for i in 0..self.n {
+            let e = self.keys[i].as_ref().unwrap();
+            let at = 3 + i * slot;
+            e.key.encode(&mut record[at..at + K::SIZE]);
+            e.value.encode(&mut record[at + K::SIZE..at + slot]);
+        }
+
+        if !self.leaf {
+            for i in 0..=self.n {
+                self.child[i].as_ref().unwrap().encode_compact(buf);
+            }
+        }
+    }
+
+    // Decodes the subtree starting at pos, checking every leaf is at the same depth and no deeper than
+    // max_depth, and every node but the root holds at least t - 1 keys
+    fn decode_compact(
+        t: usize,
+        buf: &[u8],
+        pos: &mut usize,
+        depth: usize,
+        max_depth: usize,
+        leaf_depth: &mut Option<usize>,
+    ) -> Result<Node<K, P>, DecodeError> {
+        // Bounds the recursion, however the records are crafted
+        if depth >= max_depth {
+            return Err(DecodeError::InvalidStructure);
+        }
+
+        let size = record_size::<K, P>(t);
+        if buf.len() - *pos < size {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+
+        let record = &buf[*pos..*pos + size];
+        let n = u16::decode(&record[1..3]) as usize;
+        let min = if depth == 0 { 1 } else { t - 1 };
+        if record[0] > 1 || n < min || n > 2 * t - 1 {
+            return Err(DecodeError::InvalidNode { offset: *pos });
+        }
+        *pos += size;
+
+        let mut node = Node::new(t, record[0] == 1);
+        let slot = K::SIZE + P::SIZE;
+        for i in 0..n {
+            let at = 3 + i * slot;
+            node.keys[i] = Some(Entry::new(
+                K::decode(&record[at..at + K::SIZE]),
+                P::decode(&record[at + K::SIZE..at + slot]),
+            ));
+        }
+        node.n = n;
+        node.size = n;
+
+        if node.leaf {
+            if *leaf_depth.get_or_insert(depth) != depth {
+                return Err(DecodeError::InvalidStructure);
+            }
+        } else {
+            for i in 0..=n {
+                let c = Node::decode_compact(t, buf, pos, depth + 1, max_depth, leaf_depth)?;
+                node.size += c.size;
+                node.child[i] = Some(c);
+            }
+        }
+
+        Ok(node)
+    }
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy + FixedSized,
+    P: Copy + FixedSized,
+{
+    /// Encodes the tree into a compact binary layout of fixed-size node records in pre-order,
+    /// see `decode_compact`. The degree has to fit in a byte and the entry count in 32 bits.
+    pub fn encode_compact(&self) -> Vec<u8> {
+        if self.t > u8::MAX as usize || self.len() > u32::MAX as usize {
+            panic!("Tree is too large for the compact encoding");
+        }
+
+        let mut buf = Vec::with_capacity(HEADER_SIZE + self.len() * (K::SIZE + P::SIZE));
+        buf.push(self.t as u8);
+        buf.extend_from_slice(&(self.len() as u32).to_le_bytes());
+        if let Some(r) = &self.root {
+            r.encode_compact(&mut buf);
+        }
+        buf
+    }
+
+    /// Decodes a tree written by `encode_compact`, validating its structure along the way:
+    /// every node within its key bounds, leaves at the same depth, keys in order and the entry count.
+    pub fn decode_compact(bytes: &[u8]) -> Result<BTree<K, P>, DecodeError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+
+        let t = bytes[0] as usize;
+        if t < 2 {
+            return Err(DecodeError::InvalidDegree(t));
+        }
+        let count = u32::decode(&bytes[1..5]) as usize;
+
+        let mut tree = BTree::new(t);
+        let mut pos = HEADER_SIZE;
+        if count > 0 {
+            let max_depth = max_height(t, count);
+            let root = Node::decode_compact(t, bytes, &mut pos, 0, max_depth, &mut None)?;
+            if root.size != count {
+                return Err(DecodeError::CountMismatch {
+                    expected: count,
+                    found: root.size,
+                });
+            }
+            tree.root = Some(root);
+            tree.refresh_extremes();
+        }
+
+        if pos != bytes.len() {
+            return Err(DecodeError::TrailingBytes(bytes.len() - pos));
+        }
+
+        let mut iter = tree.iter();
+        let mut prev = iter.next();
+        for e in iter {
+            // Equal keys are fine, the tree holds duplicates
+            if prev.unwrap().key > e.key {
+                return Err(DecodeError::InvalidStructure);
+            }
+            prev = Some(e);
+        }
+
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{keys, lcg};
+    use crate::{BTree, DecodeError};
+
+    fn encoded(t: usize, n: u64) -> Vec<u8> {
+        BTree::from_sorted_iter(t, (0..n).map(|i| (i, i * 2))).encode_compact()
+    }
+
+    fn header(t: u8, count: u32) -> Vec<u8> {
+        let mut bytes = vec![t];
+        bytes.extend_from_slice(&count.to_le_bytes());
+        bytes
+    }
+
+    // A node record of u64 keys and values, with the values equal to the keys
+    fn record(t: u8, leaf: bool, keys: &[u64]) -> Vec<u8> {
+        let mut bytes = vec![leaf as u8];
+        bytes.extend_from_slice(&(keys.len() as u16).to_le_bytes());
+        for &k in keys {
+            bytes.extend_from_slice(&k.to_le_bytes());
+            bytes.extend_from_slice(&k.to_le_bytes());
+        }
+        bytes.resize(3 + (2 * t as usize - 1) * 16, 0);
+        bytes
+    }
+
+    #[test]
+    fn round_trip_keeps_duplicates() {
+        let mut tree = BTree::new(3);
+        let mut seed = 163;
+        for _ in 0..2000 {
+            let key = lcg(&mut seed) % 300;
+            tree.insert(key, key + 1);
+        }
+
+        let decoded = BTree::<u64, u64>::decode_compact(&tree.encode_compact()).unwrap();
+        assert_eq!(keys(&decoded), keys(&tree));
+        assert!(decoded.iter().all(|e| e.value == e.key + 1));
+        decoded.validate_sizes().unwrap();
+        assert!(decoded.is_balanced());
+
+        let empty = BTree::<u64, u64>::new(4).encode_compact();
+        assert!(BTree::<u64, u64>::decode_compact(&empty)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        let bytes = encoded(3, 1000);
+        let decode = |b: &[u8]| BTree::<u64, u64>::decode_compact(b).map(|_| ());
+
+        assert_eq!(decode(&bytes[..3]), Err(DecodeError::UnexpectedEnd));
+        assert_eq!(
+            decode(&bytes[..bytes.len() - 1]),
+            Err(DecodeError::UnexpectedEnd)
+        );
+
+        let mut trailing = bytes.clone();
+        trailing.extend_from_slice(&[0, 0]);
+        assert_eq!(decode(&trailing), Err(DecodeError::TrailingBytes(2)));
+
+        for t in [0u8, 1].iter() {
+            let mut degree = bytes.clone();
+            degree[0] = *t;
+            assert_eq!(
+                decode(&degree),
+                Err(DecodeError::InvalidDegree(*t as usize))
+            );
+        }
+
+        // Keys out of order within a node
+        let mut unsorted = header(2, 2);
+        unsorted.extend(record(2, true, &[2, 1]));
+        assert_eq!(decode(&unsorted), Err(DecodeError::InvalidStructure));
+    }
+
+    #[test]
+    fn underfull_nodes_are_rejected() {
+        // A root over two leaves with one key each is full enough at t = 2, but not at t = 3
+        for &(t, valid) in [(2, true), (3, false)].iter() {
+            let mut bytes = header(t, 3);
+            bytes.extend(record(t, false, &[2]));
+            bytes.extend(record(t, true, &[1]));
+            bytes.extend(record(t, true, &[3]));
+            assert_eq!(BTree::<u64, u64>::decode_compact(&bytes).is_ok(), valid);
+        }
+
+        // The root may hold a single key at any degree
+        let mut bytes = header(4, 1);
+        bytes.extend(record(4, true, &[1]));
+        assert!(BTree::<u64, u64>::decode_compact(&bytes).is_ok());
+    }
+
+    #[test]
+    fn deep_chains_are_cut_off() {
+        // A header promising many entries, followed by a long chain of internal nodes with one key
+        let mut bytes = header(2, u32::MAX);
+        for _ in 0..200_000 {
+            bytes.extend(record(2, false, &[1]));
+        }
+
+        assert_eq!(
+            BTree::<u64, u64>::decode_compact(&bytes).map(|_| ()),
+            Err(DecodeError::InvalidStructure)
+        );
+    }
+}
@@ -0,0 +1,171 @@
+#[cfg(feature = "access_tracking")]
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "access_tracking")]
+use std::sync::Mutex;
+
+use crate::NodePool;
+
+/// A structural change made to the tree while inserting or removing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event<K> {
+    /// A full node was split, promoting the separator key to its parent
+    Split(K),
+    /// Two siblings were merged around the separator key pulled down from their parent
+    Merge(K),
+}
+
+/// How an insert makes room in a full node on its way down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplitStrategy {
+    /// Always split the full node in two
+    #[default]
+    Classic,
+    /// Like a B*-tree, first try to rotate a key into the right or else the left sibling,
+    /// and only split when neither has room to spare. Keeps nodes fuller at the cost of touching more of them.
+    BStarDeferred,
+}
+
+/// Lifetime operation counters of a tree, see `BTree::with_stats`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OpStats {
+    pub inserts: u64,
+    pub removes: u64,
+    pub searches: u64,
+    pub splits: u64,
+    pub merges: u64,
+}
+
+/// The counters behind `OpStats`. Atomic, so lookups through a shared reference can count
+/// without making the tree `!Sync`.
+#[derive(Debug, Default)]
+pub struct AtomicStats {
+    pub inserts: AtomicU64,
+    pub removes: AtomicU64,
+    pub searches: AtomicU64,
+    pub splits: AtomicU64,
+    pub merges: AtomicU64,
+}
+
+impl AtomicStats {
+    pub fn load(&self) -> OpStats {
+        OpStats {
+            inserts: self.inserts.load(Ordering::Relaxed),
+            removes: self.removes.load(Ordering::Relaxed),
+            searches: self.searches.load(Ordering::Relaxed),
+            splits: self.splits.load(Ordering::Relaxed),
+            merges: self.merges.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn store(&self, stats: OpStats) {
+        self.inserts.store(stats.inserts, Ordering::Relaxed);
+        self.removes.store(stats.removes, Ordering::Relaxed);
+        self.searches.store(stats.searches, Ordering::Relaxed);
+        self.splits.store(stats.splits, Ordering::Relaxed);
+        self.merges.store(stats.merges, Ordering::Relaxed);
+    }
+}
+
+// Callback receiving the separator of every split
+type SplitObserver<K> = Box<dyn FnMut(&K) + Send + Sync>;
+
+// Combines the old and the new value of a key that's inserted again
+type MergeFn<P> = Box<dyn Fn(P, P) -> P + Send + Sync>;
+
+/// State of the tree that node operations need while they restructure it
+pub struct Context<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    pub pool: NodePool<K, P>,
+    // Only collected while someone is listening
+    pub events: Option<Vec<Event<K>>>,
+    // Called with the separator of every split, see `BTree::observe_splits`
+    pub split_observer: Option<SplitObserver<K>>,
+    // Only counted when enabled
+    pub stats: Option<AtomicStats>,
+    pub split_strategy: SplitStrategy,
+    // Resolves inserts of a key that's already there, see `BTree::with_merge_fn`
+    pub merge_fn: Option<MergeFn<P>>,
+    // Lookup hits per key, only of keys in the tree. Entries are Copy, so the counters can't live in them.
+    #[cfg(feature = "access_tracking")]
+    pub hits: Mutex<BTreeMap<K, u64>>,
+}
+
+impl<K, P> Context<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    pub fn new() -> Context<K, P> {
+        Context {
+            pool: NodePool::new(false),
+            events: None,
+            split_observer: None,
+            stats: None,
+            split_strategy: SplitStrategy::Classic,
+            merge_fn: None,
+            #[cfg(feature = "access_tracking")]
+            hits: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    // Adds one to the counter f picks
+    pub fn count<F: FnOnce(&AtomicStats) -> &AtomicU64>(&self, f: F) {
+        if let Some(stats) = &self.stats {
+            f(stats).fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[cfg(feature = "access_tracking")]
+    pub fn hit(&self, key: K) {
+        *self.hits.lock().unwrap().entry(key).or_insert(0) += 1;
+    }
+
+    pub fn record(&mut self, event: Event<K>) {
+        self.count(|s| match event {
+            Event::Split(_) => &s.splits,
+            Event::Merge(_) => &s.merges,
+        });
+
+        if let (Event::Split(key), Some(f)) = (&event, &mut self.split_observer) {
+            f(key);
+        }
+
+        if let Some(events) = &mut self.events {
+            events.push(event);
+        }
+    }
+}
+
+impl<K, P> fmt::Debug for Context<K, P>
+where
+    K: Eq + Ord + Copy + fmt::Debug,
+    P: Copy + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("Context");
+        d.field("pool", &self.pool)
+            .field("events", &self.events)
+            .field("split_observer", &self.split_observer.is_some())
+            .field("stats", &self.stats.as_ref().map(AtomicStats::load))
+            .field("split_strategy", &self.split_strategy)
+            .field("merge_fn", &self.merge_fn.is_some());
+        #[cfg(feature = "access_tracking")]
+        d.field("hits", &self.hits);
+        d.finish()
+    }
+}
+
+impl<K, P> Default for Context<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    fn default() -> Self {
+        Context::new()
+    }
+}
@@ -0,0 +1,158 @@
+use std::fmt::Display;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use crate::{BTree, CsvError};
+
+// The csv crate takes single byte delimiters
+fn delimiter_byte(delimiter: char) -> Result<u8, CsvError> {
+    if !delimiter.is_ascii() {
+        return Err(CsvError::Delimiter(delimiter));
+    }
+    Ok(delimiter as u8)
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Writes one `key,value` row per entry, in key order, separated by delimiter and without a header.
+    /// A delimiter that isn't ASCII is reported as `CsvError::Delimiter`.
+    pub fn export_csv<W: Write>(&self, writer: W, delimiter: char) -> Result<(), CsvError>
+    where
+        K: Display,
+        P: Display,
+    {
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(false)
+            .delimiter(delimiter_byte(delimiter)?)
+            .from_writer(writer);
+
+        for e in self.iter() {
+            writer.write_record([e.key.to_string(), e.value.to_string()])?;
+        }
+        writer.flush().map_err(csv::Error::from)?;
+        Ok(())
+    }
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy + FromStr,
+    P: Copy + FromStr,
+{
+    /// Loads a tree from CSV rows of `key,value`, without a header, that are sorted by key without duplicates.
+    /// Rows out of order are reported as `CsvError::Unsorted`, a repeated key as `CsvError::Duplicate`.
+    /// The rows are bulk-loaded like `from_sorted_iter`. Keys and values are parsed with `FromStr`,
+    /// surrounding whitespace is trimmed.
+    pub fn from_csv<R: Read>(t: usize, reader: R) -> Result<BTree<K, P>, CsvError> {
+        BTree::import_csv(t, reader, ',')
+    }
+
+    /// Like `from_csv`, but with the fields separated by delimiter, reads what `export_csv` writes.
+    /// A delimiter that isn't ASCII is reported as `CsvError::Delimiter`.
+    pub fn import_csv<R: Read>(
+        t: usize,
+        reader: R,
+        delimiter: char,
+    ) -> Result<BTree<K, P>, CsvError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(delimiter_byte(delimiter)?)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(reader);
+
+        let mut entries: Vec<(K, P)> = Vec::new();
+        for row in reader.records() {
+            let row = row?;
+            let line = row.position().map_or(0, |p| p.line());
+
+            if row.len() != 2 {
+                return Err(CsvError::Malformed { line });
+            }
+            let key = row[0].parse().map_err(|_| CsvError::Malformed { line })?;
+            let value = row[1].parse().map_err(|_| CsvError::Malformed { line })?;
+
+            match entries.last() {
+                Some(&(prev, _)) if prev > key => return Err(CsvError::Unsorted { line }),
+                Some(&(prev, _)) if prev == key => return Err(CsvError::Duplicate { line }),
+                _ => {}
+            }
+            entries.push((key, value));
+        }
+
+        Ok(BTree::from_sorted_iter(t, entries))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{assert_valid, keys};
+    use crate::{BTree, CsvError};
+
+    #[test]
+    fn from_csv_loads_sorted_rows() {
+        let data = "1,10\n2, 20\n 5 ,50\n8,80\n13,130\n";
+        let tree: BTree<u32, u64> = BTree::from_csv(2, data.as_bytes()).unwrap();
+
+        assert_valid(&tree);
+        assert_eq!(keys(&tree), [1, 2, 5, 8, 13]);
+        assert!(tree.iter().all(|e| e.value == u64::from(e.key) * 10));
+    }
+
+    #[test]
+    fn from_csv_reports_the_bad_line() {
+        let bad_value = "1,10\n2,20\nthree,30\n4,40\n";
+        match BTree::<u32, u32>::from_csv(2, bad_value.as_bytes()) {
+            Err(CsvError::Malformed { line }) => assert_eq!(line, 3),
+            other => panic!("Expected a malformed row, got {:?}", other),
+        }
+
+        let missing_value = "1,10\n2\n";
+        match BTree::<u32, u32>::from_csv(2, missing_value.as_bytes()) {
+            Err(CsvError::Malformed { line }) => assert_eq!(line, 2),
+            other => panic!("Expected a malformed row, got {:?}", other),
+        }
+
+        let unsorted = "1,10\n5,50\n4,40\n";
+        match BTree::<u32, u32>::from_csv(2, unsorted.as_bytes()) {
+            Err(CsvError::Unsorted { line }) => assert_eq!(line, 3),
+            other => panic!("Expected unsorted rows, got {:?}", other),
+        }
+
+        let duplicate = "1,10\n4,40\n4,41\n";
+        match BTree::<u32, u32>::from_csv(2, duplicate.as_bytes()) {
+            Err(CsvError::Duplicate { line }) => assert_eq!(line, 3),
+            other => panic!("Expected a duplicate key, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn non_ascii_delimiters_are_rejected() {
+        match BTree::<u32, u32>::import_csv(2, "1§10\n".as_bytes(), '§') {
+            Err(CsvError::Delimiter(c)) => assert_eq!(c, '§'),
+            other => panic!("Expected a bad delimiter, got {:?}", other),
+        }
+
+        let tree = BTree::from_sorted_iter(2, (0..10u32).map(|i| (i, i)));
+        let mut out = Vec::new();
+        assert!(matches!(
+            tree.export_csv(&mut out, '→'),
+            Err(CsvError::Delimiter('→'))
+        ));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn export_then_import_round_trips() {
+        let tree = BTree::from_sorted_iter(3, (0..500i64).map(|i| (i - 250, i * 7)));
+        let mut out = Vec::new();
+        tree.export_csv(&mut out, ';').unwrap();
+
+        let back: BTree<i64, i64> = BTree::import_csv(3, &out[..], ';').unwrap();
+        let pairs = |t: &BTree<i64, i64>| t.iter().map(|e| (e.key, e.value)).collect::<Vec<_>>();
+        assert_eq!(pairs(&back), pairs(&tree));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{BTree, Entry, Node};
+
+/// A position in a `BTree`, for navigating between its leaves
+pub struct Cursor<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    // Path from the root to the current entry. The last frame holds the index of the current key,
+    // every frame above it the index of the child that was descended into.
+    stack: Vec<(&'a Node<K, P>, usize)>,
+}
+
+impl<'a, K, P> Cursor<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Returns the entry at the cursor, or None if it's past the last entry
+    pub fn entry(&self) -> Option<&'a Entry<K, P>> {
+        let &(node, i) = self.stack.last()?;
+        node.keys[..node.n].get(i)?.as_ref()
+    }
+
+    /// Moves to the first entry of the leaf after the current one, in key order.
+    /// From an entry in an internal node, that's the first leaf right of the entry.
+    /// Returns None, leaving the cursor in place, if there is no next leaf.
+    pub fn next_leaf(&mut self) -> Option<&'a Entry<K, P>> {
+        self.entry()?;
+        let mut stack = self.stack.clone();
+        let &(node, _) = stack.last()?;
+
+        // Climb to the nearest ancestor with a child right of the path
+        if node.leaf {
+            stack.pop();
+            while stack.last().is_some_and(|&(n, c)| c == n.n) {
+                stack.pop();
+            }
+        }
+        let (node, c) = stack.last_mut()?;
+        *c += 1;
+
+        let child = node.child[*c].as_ref().unwrap();
+        Cursor::push_leftmost(&mut stack, child);
+        self.stack = stack;
+        self.entry()
+    }
+
+    /// Moves to the first entry of the leaf before the current one, in key order.
+    /// From an entry in an internal node, that's the last leaf left of the entry.
+    /// Returns None, leaving the cursor in place, if there is no previous leaf.
+    pub fn prev_leaf(&mut self) -> Option<&'a Entry<K, P>> {
+        let mut stack = self.stack.clone();
+        let &(node, _) = stack.last()?;
+
+        // Climb to the nearest ancestor with a child left of the path
+        if node.leaf {
+            stack.pop();
+            while stack.last().is_some_and(|&(_, c)| c == 0) {
+                stack.pop();
+            }
+            let (_, c) = stack.last_mut()?;
+            *c -= 1;
+        }
+        let &(node, c) = stack.last()?;
+
+        // Down the right-most path of the child, to the first entry of its last leaf
+        let mut node = node.child[c].as_ref().unwrap();
+        while !node.leaf {
+            stack.push((node, node.n));
+            node = node.child[node.n].as_ref().unwrap();
+        }
+        stack.push((node, 0));
+
+        self.stack = stack;
+        self.entry()
+    }
+
+    // Pushes the path down to the first entry of the left-most leaf of the subtree
+    fn push_leftmost(stack: &mut Vec<(&'a Node<K, P>, usize)>, mut node: &'a Node<K, P>) {
+        loop {
+            stack.push((node, 0));
+            if node.leaf {
+                return;
+            }
+            node = node.child[0].as_ref().unwrap();
+        }
+    }
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Returns a cursor at the first entry whose key isn't smaller than key
+    pub fn cursor_at(&self, key: &K) -> Cursor<'_, K, P> {
+        let mut cursor = Cursor { stack: Vec::new() };
+        let mut node = match &self.root {
+            Some(r) => r,
+            None => return cursor,
+        };
+
+        loop {
+            let i = node.find_key(key);
+            cursor.stack.push((node, i));
+            if node.leaf || (i < node.n && node.keys[i].as_ref().unwrap().key == *key) {
+                break;
+            }
+            node = node.child[i].as_ref().unwrap();
+        }
+
+        // Past the end of a leaf, the next entry is the separator after it in the nearest ancestor that has one
+        while cursor.stack.len() > 1 && cursor.entry().is_none() {
+            cursor.stack.pop();
+        }
+        cursor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BTree;
+
+    #[test]
+    fn next_and_prev_leaf_step_between_leaves() {
+        let mut tree = BTree::new(3);
+        for i in 0..500u32 {
+            tree.insert(i, i);
+        }
+
+        let leaves: Vec<Vec<u32>> = tree
+            .level_iter()
+            .last()
+            .unwrap()
+            .iter()
+            .map(|leaf| leaf.iter_keys().copied().collect())
+            .collect();
+        assert!(leaves.len() > 10);
+
+        for pair in leaves.windows(2) {
+            let last = *pair[0].last().unwrap();
+            let mut cursor = tree.cursor_at(&last);
+            assert_eq!(cursor.entry().map(|e| e.key), Some(last));
+            assert_eq!(cursor.next_leaf().map(|e| e.key), Some(pair[1][0]));
+            assert_eq!(cursor.prev_leaf().map(|e| e.key), Some(pair[0][0]));
+        }
+
+        // There is nothing past the outer leaves, and the cursor stays put
+        let mut cursor = tree.cursor_at(&499);
+        assert!(cursor.next_leaf().is_none());
+        assert_eq!(cursor.entry().map(|e| e.key), Some(499));
+        let mut cursor = tree.cursor_at(&0);
+        assert!(cursor.prev_leaf().is_none());
+        assert_eq!(cursor.entry().map(|e| e.key), Some(0));
+    }
+}
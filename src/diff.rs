@@ -0,0 +1,113 @@
+use std::cmp::Ordering;
+use std::iter::Peekable;
+
+use crate::{BTree, Iter};
+
+/// A difference between an old and a new tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change<K, P> {
+    /// The key is only in the new tree
+    Added(K, P),
+    /// The key is only in the old tree
+    Removed(K, P),
+    /// The key is in both trees, with the old and the new value
+    Changed(K, P, P),
+}
+
+/// Iterator over the changes between two trees in key order, see `BTree::diff`
+pub struct Diff<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    old: Peekable<Iter<'a, K, P>>,
+    new: Peekable<Iter<'a, K, P>>,
+}
+
+impl<K, P> Iterator for Diff<'_, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy + PartialEq,
+{
+    type Item = Change<K, P>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let order = match (self.old.peek(), self.new.peek()) {
+                (Some(a), Some(b)) => a.key.cmp(&b.key),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => return None,
+            };
+
+            match order {
+                Ordering::Less => {
+                    let e = self.old.next().unwrap();
+                    return Some(Change::Removed(e.key, e.value));
+                }
+                Ordering::Greater => {
+                    let e = self.new.next().unwrap();
+                    return Some(Change::Added(e.key, e.value));
+                }
+                Ordering::Equal => {
+                    let a = self.old.next().unwrap();
+                    let b = self.new.next().unwrap();
+                    if a.value != b.value {
+                        return Some(Change::Changed(a.key, a.value, b.value));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Compares this tree, as the old snapshot, against other, as the new one, e.g. for change data capture.
+    /// Both trees are walked in key order at the same time, so the whole diff takes linear time.
+    pub fn diff<'a>(&'a self, other: &'a BTree<K, P>) -> Diff<'a, K, P> {
+        Diff {
+            old: self.iter().peekable(),
+            new: other.iter().peekable(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Change;
+    use crate::BTree;
+
+    #[test]
+    fn diff_reports_inserts_removes_and_changes() {
+        let old = BTree::from_sorted_iter(3, (0..100u32).map(|i| (i, i)));
+        let mut new = BTree::from_sorted_iter(3, (0..100u32).map(|i| (i, i)));
+        new.insert(150, 1);
+        new.remove(&0);
+        new.remove(&51);
+        new.remove(&99);
+        for k in [10, 50, 98].iter() {
+            new.remove(k);
+            new.insert(*k, k + 1000);
+        }
+
+        let changes: Vec<Change<u32, u32>> = old.diff(&new).collect();
+        assert_eq!(
+            changes,
+            [
+                Change::Removed(0, 0),
+                Change::Changed(10, 10, 1010),
+                Change::Changed(50, 50, 1050),
+                Change::Removed(51, 51),
+                Change::Changed(98, 98, 1098),
+                Change::Removed(99, 99),
+                Change::Added(150, 1),
+            ]
+        );
+
+        assert_eq!(old.diff(&old).count(), 0);
+    }
+}
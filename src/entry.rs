@@ -0,0 +1,300 @@
+use std::borrow::Borrow;
+use std::convert::Infallible;
+
+use crate::{BTree, Entry};
+
+/// A view into a single key of a `BTree`, which is either occupied or vacant
+pub enum BtreeEntry<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    Occupied(OccupiedEntry<'a, K, P>),
+    Vacant(VacantEntry<'a, K, P>),
+}
+
+pub struct OccupiedEntry<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    entry: &'a mut Entry<K, P>,
+}
+
+pub struct VacantEntry<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    tree: &'a mut BTree<K, P>,
+    key: K,
+}
+
+impl<'a, K, P> BtreeEntry<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    pub fn key(&self) -> &K {
+        match self {
+            BtreeEntry::Occupied(e) => e.key(),
+            BtreeEntry::Vacant(e) => e.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: P) -> &'a mut P {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> P>(self, f: F) -> &'a mut P {
+        match self {
+            BtreeEntry::Occupied(e) => e.into_mut(),
+            BtreeEntry::Vacant(e) => e.insert(f()),
+        }
+    }
+
+    pub fn and_modify<F: FnOnce(&mut P)>(mut self, f: F) -> Self {
+        if let BtreeEntry::Occupied(e) = &mut self {
+            f(e.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, K, P> OccupiedEntry<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    pub fn key(&self) -> &K {
+        &self.entry.key
+    }
+
+    pub fn get(&self) -> &P {
+        &self.entry.value
+    }
+
+    pub fn get_mut(&mut self) -> &mut P {
+        &mut self.entry.value
+    }
+
+    pub fn into_mut(self) -> &'a mut P {
+        &mut self.entry.value
+    }
+
+    /// Replaces the value, returning the old one
+    pub fn insert(&mut self, value: P) -> P {
+        std::mem::replace(&mut self.entry.value, value)
+    }
+}
+
+impl<'a, K, P> VacantEntry<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn insert(self, value: P) -> &'a mut P {
+        let tree = self.tree;
+        tree.insert(self.key, value);
+
+        // Splits on the way down may have moved the entry, so look it up at its final position
+        let e = tree.root.as_mut().unwrap().find_mut(&self.key).unwrap();
+        &mut e.value
+    }
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    pub fn entry(&mut self, key: K) -> BtreeEntry<'_, K, P> {
+        self.entry_with(&key, |k| *k)
+    }
+
+    /// Like `entry`, but looks the key up through a borrowed form of it, e.g. an inline string key by `&str`.
+    /// The owned key is only built, through `From`, when the entry turns out to be vacant.
+    /// Keys have to be `Copy`, so this doesn't make `String` keys possible, it's meant for `Copy` keys
+    /// that have a borrowed form which is cheaper to come by.
+    pub fn entry_ref<'q, Q>(&mut self, key: &'q Q) -> BtreeEntry<'_, K, P>
+    where
+        K: Borrow<Q> + From<&'q Q>,
+        Q: Ord + ?Sized,
+    {
+        self.entry_with(key, K::from)
+    }
+
+    // Looks key up, building the owned key with to_owned only if the entry is vacant
+    fn entry_with<'q, Q, F>(&mut self, key: &'q Q, to_owned: F) -> BtreeEntry<'_, K, P>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        F: FnOnce(&'q Q) -> K,
+    {
+        if self.get(key).is_none() {
+            return BtreeEntry::Vacant(VacantEntry {
+                tree: self,
+                key: to_owned(key),
+            });
+        }
+
+        let entry = self.root.as_mut().unwrap().find_mut(key).unwrap();
+        BtreeEntry::Occupied(OccupiedEntry { entry })
+    }
+
+    /// Returns the value for key, inserting the one built by f if the key is absent.
+    /// The key is looked up before f is called, so if f fails its error is returned
+    /// and the tree is left exactly as it was, down to the shape of its nodes.
+    pub fn get_or_try_insert_with<E, F>(&mut self, key: K, f: F) -> Result<&mut P, E>
+    where
+        F: FnOnce() -> Result<P, E>,
+    {
+        if self.root.as_ref().and_then(|r| r.find(&key)).is_none() {
+            let value = f()?;
+            return Ok(self.insert_ref(key, value));
+        }
+
+        self.ctx.count(|s| &s.searches);
+        #[cfg(feature = "access_tracking")]
+        self.ctx.hit(key);
+        let e = self.root.as_mut().unwrap().find_mut(&key).unwrap();
+        Ok(&mut e.value)
+    }
+
+    /// Inserts the entry only if key isn't in the tree yet, leaving an existing value unchanged.
+    /// Returns whether it was inserted.
+    pub fn insert_if_absent(&mut self, key: K, value: P) -> bool {
+        let len = self.len();
+        let _ = self.get_or_try_insert_with(key, || Ok::<P, Infallible>(value));
+        self.len() > len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Borrow;
+    use std::cell::Cell;
+    use std::cmp::Ordering;
+
+    use crate::tests::assert_valid;
+    use crate::BTree;
+
+    thread_local! {
+        static BUILT: Cell<usize> = const { Cell::new(0) };
+    }
+
+    // Inline string of up to 16 bytes, which unlike String is Copy
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct ShortName {
+        len: usize,
+        bytes: [u8; 16],
+    }
+
+    impl ShortName {
+        fn as_str(&self) -> &str {
+            std::str::from_utf8(&self.bytes[..self.len]).unwrap()
+        }
+    }
+
+    impl Borrow<str> for ShortName {
+        fn borrow(&self) -> &str {
+            self.as_str()
+        }
+    }
+
+    impl From<&str> for ShortName {
+        fn from(s: &str) -> ShortName {
+            BUILT.with(|b| b.set(b.get() + 1));
+            let mut bytes = [0; 16];
+            bytes[..s.len()].copy_from_slice(s.as_bytes());
+            ShortName {
+                len: s.len(),
+                bytes,
+            }
+        }
+    }
+
+    impl Ord for ShortName {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.as_str().cmp(other.as_str())
+        }
+    }
+
+    impl PartialOrd for ShortName {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    #[test]
+    fn entry_counts_repeated_keys() {
+        let mut tree: BTree<u32, u32> = BTree::new(2);
+        for i in 0..200 {
+            *tree.entry(i % 50).or_insert(0) += 1;
+        }
+
+        assert_eq!(tree.len(), 50);
+        assert!(tree.values().all(|v| *v == 4));
+    }
+
+    #[test]
+    fn entry_ref_looks_up_by_str() {
+        let mut tree: BTree<ShortName, u32> = BTree::new(2);
+        let words = ["apple", "pear", "fig", "apple", "plum", "fig", "apple"];
+        for word in words.iter() {
+            // Looking up through a slice of a heap string, not a ShortName
+            let owned = word.to_string();
+            *tree.entry_ref(owned.as_str()).or_insert(0) += 1;
+        }
+
+        assert_eq!(tree.len(), 4);
+        assert_eq!(tree.get("apple"), Some(&3));
+        assert_eq!(tree.get("fig"), Some(&2));
+        assert_eq!(tree.get("kiwi"), None);
+
+        // Only the vacant lookups built an owned key
+        assert_eq!(BUILT.with(|b| b.get()), 4);
+    }
+
+    #[test]
+    fn get_or_try_insert_with_leaves_the_key_absent_on_error() {
+        let mut tree = BTree::new(2);
+        for i in 0..100u32 {
+            tree.insert(i * 2, i);
+        }
+
+        // Odd keys are missing, and failing to build them changes nothing, not even how the nodes are split
+        let layout = |tree: &BTree<u32, u32>| -> Vec<Vec<Vec<u32>>> {
+            tree.level_iter()
+                .map(|level| {
+                    level
+                        .iter()
+                        .map(|node| node.iter_keys().copied().collect())
+                        .collect()
+                })
+                .collect()
+        };
+        let before = layout(&tree);
+        for k in (1..200).step_by(2) {
+            let result: Result<&mut u32, &str> = tree.get_or_try_insert_with(k, || Err("failed"));
+            assert_eq!(result, Err("failed"));
+            assert_eq!(tree.get(&k), None);
+            assert_eq!(layout(&tree), before);
+        }
+        assert_eq!(tree.len(), 100);
+        assert_valid(&tree);
+
+        // Present keys don't call the constructor at all
+        let value = tree.get_or_try_insert_with(10, || -> Result<u32, &str> { panic!("called") });
+        assert_eq!(value, Ok(&mut 5));
+
+        let value = tree.get_or_try_insert_with(11, || -> Result<u32, &str> { Ok(1000) });
+        assert_eq!(value, Ok(&mut 1000));
+        assert_eq!(tree.len(), 101);
+        assert_valid(&tree);
+    }
+}
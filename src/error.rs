@@ -0,0 +1,165 @@
+use std::error::Error;
+use std::fmt;
+
+/// Two entries ended up with the same key, where keys have to be unique
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuplicateKeyError<K> {
+    pub key: K,
+}
+
+impl<K: fmt::Debug> fmt::Display for DuplicateKeyError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "duplicate key {:?}", self.key)
+    }
+}
+
+impl<K: fmt::Debug> Error for DuplicateKeyError<K> {}
+
+/// The first difference found between a tree and the entries it was expected to hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyError<K, P> {
+    LengthMismatch { expected: usize, found: usize },
+    KeyMismatch { index: usize, expected: K, found: K },
+    ValueMismatch { index: usize, expected: P, found: P },
+}
+
+impl<K: fmt::Debug, P: fmt::Debug> fmt::Display for VerifyError<K, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::LengthMismatch { expected, found } => {
+                write!(f, "expected {} entries, found {}", expected, found)
+            }
+            VerifyError::KeyMismatch {
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "expected key {:?} at index {}, found {:?}",
+                expected, index, found
+            ),
+            VerifyError::ValueMismatch {
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "expected value {:?} at index {}, found {:?}",
+                expected, index, found
+            ),
+        }
+    }
+}
+
+impl<K: fmt::Debug, P: fmt::Debug> Error for VerifyError<K, P> {}
+
+/// Bytes that couldn't be decoded into a tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended in the middle of the header or a node
+    UnexpectedEnd,
+    /// The degree is smaller than 2
+    InvalidDegree(usize),
+    /// A node has an invalid leaf flag or key count
+    InvalidNode { offset: usize },
+    /// The nodes don't form a valid tree, their keys are out of order or leaves are at different depths
+    InvalidStructure,
+    /// The nodes hold a different number of entries than the header says
+    CountMismatch { expected: usize, found: usize },
+    /// There are bytes left after the last node
+    TrailingBytes(usize),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "unexpected end of input"),
+            DecodeError::InvalidDegree(t) => write!(f, "invalid degree {}", t),
+            DecodeError::InvalidNode { offset } => write!(f, "invalid node at offset {}", offset),
+            DecodeError::InvalidStructure => write!(f, "nodes don't form a valid tree"),
+            DecodeError::CountMismatch { expected, found } => {
+                write!(f, "expected {} entries, found {}", expected, found)
+            }
+            DecodeError::TrailingBytes(n) => write!(f, "{} trailing bytes", n),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// A budgeted search ran out of comparisons before it could find the key or rule it out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetExceeded;
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "comparison budget exceeded")
+    }
+}
+
+impl Error for BudgetExceeded {}
+
+/// An operation that was refused, leaving the tree unchanged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BTreeError {
+    /// The tree already holds as many entries as `BTree::set_max_len` allows
+    CapacityExceeded { limit: usize },
+}
+
+impl fmt::Display for BTreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BTreeError::CapacityExceeded { limit } => {
+                write!(f, "tree is limited to {} entries", limit)
+            }
+        }
+    }
+}
+
+impl Error for BTreeError {}
+
+/// A CSV file that couldn't be loaded into a tree, lines are counted from 1
+#[cfg(feature = "csv")]
+#[derive(Debug)]
+pub enum CsvError {
+    /// The reader failed or the CSV itself is invalid
+    Read(csv::Error),
+    /// The row isn't a `key,value` pair that parses into the key and value types
+    Malformed { line: u64 },
+    /// The key is less than the key of the row before it
+    Unsorted { line: u64 },
+    /// The key is equal to the key of the row before it
+    Duplicate { line: u64 },
+    /// The delimiter isn't an ASCII character, the only kind the csv crate can split on
+    Delimiter(char),
+}
+
+#[cfg(feature = "csv")]
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Read(e) => write!(f, "failed to read CSV: {}", e),
+            CsvError::Malformed { line } => write!(f, "malformed row on line {}", line),
+            CsvError::Unsorted { line } => write!(f, "key on line {} is out of order", line),
+            CsvError::Duplicate { line } => write!(f, "key on line {} is a duplicate", line),
+            CsvError::Delimiter(c) => write!(f, "delimiter {:?} isn't an ASCII character", c),
+        }
+    }
+}
+
+#[cfg(feature = "csv")]
+impl Error for CsvError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CsvError::Read(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "csv")]
+impl From<csv::Error> for CsvError {
+    fn from(e: csv::Error) -> CsvError {
+        CsvError::Read(e)
+    }
+}
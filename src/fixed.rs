@@ -0,0 +1,32 @@
+/// A type that is always encoded in the same number of bytes, so it can be stored in fixed-size records
+pub trait FixedSized: Sized {
+    const SIZE: usize;
+
+    /// Writes the value into the first `SIZE` bytes of buf
+    fn encode(&self, buf: &mut [u8]);
+
+    /// Reads a value back from the first `SIZE` bytes of buf
+    fn decode(buf: &[u8]) -> Self;
+}
+
+macro_rules! fixed_sized_int {
+    ($($t:ty),*) => {
+        $(
+            impl FixedSized for $t {
+                const SIZE: usize = std::mem::size_of::<$t>();
+
+                fn encode(&self, buf: &mut [u8]) {
+                    buf[..Self::SIZE].copy_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(buf: &[u8]) -> Self {
+                    let mut bytes = [0; std::mem::size_of::<$t>()];
+                    bytes.copy_from_slice(&buf[..Self::SIZE]);
+                    <$t>::from_le_bytes(bytes)
+                }
+            }
+        )*
+    };
+}
+
+fixed_sized_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
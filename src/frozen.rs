@@ -0,0 +1,74 @@
+use crate::BTree;
+
+/// Read-only snapshot of a `BTree` as a sorted array, for lookups without chasing node pointers
+#[derive(Debug, Clone)]
+pub struct FrozenIndex<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    entries: Vec<(K, P)>,
+}
+
+impl<K, P> FrozenIndex<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &K) -> Option<&P> {
+        let i = self.entries.binary_search_by_key(key, |&(k, _)| k).ok()?;
+        Some(&self.entries[i].1)
+    }
+
+    /// Exposes the sorted entries directly, e.g. for `partition_point` or SIMD code
+    pub fn as_slice(&self) -> &[(K, P)] {
+        &self.entries
+    }
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Copies the entries, in key order, into a `FrozenIndex`
+    pub fn freeze(&self) -> FrozenIndex<K, P> {
+        FrozenIndex {
+            entries: self.iter().map(|e| (e.key, e.value)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::lcg;
+    use crate::BTree;
+
+    #[test]
+    fn as_slice_exposes_the_sorted_entries() {
+        let mut tree = BTree::new(3);
+        let mut seed = 182;
+        for _ in 0..1000 {
+            let key = lcg(&mut seed) % 5000;
+            tree.insert(key, key + 1);
+        }
+
+        let frozen = tree.freeze();
+        let slice = frozen.as_slice();
+        assert_eq!(slice.len(), tree.len());
+        assert!(slice.windows(2).all(|w| w[0].0 <= w[1].0));
+        assert!(slice.iter().all(|&(k, v)| v == k + 1));
+
+        // Callers can search the slice themselves
+        let below = slice.partition_point(|&(k, _)| k < 2500);
+        assert_eq!(below, tree.range(..2500).count());
+    }
+}
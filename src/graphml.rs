@@ -0,0 +1,90 @@
+use std::fmt::{Display, Write};
+
+use crate::{BTree, Node};
+
+// Escapes the characters that can't appear as is in XML text or attribute values
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+impl<K, P> Node<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    // Writes this subtree in pre-order, numbering the nodes from next_id on, returns the id of this node
+    fn write_graphml(&self, nodes: &mut String, edges: &mut String, next_id: &mut usize) -> usize
+    where
+        K: Display,
+    {
+        let id = *next_id;
+        *next_id += 1;
+
+        let keys: Vec<String> = self.iter_keys().map(|k| k.to_string()).collect();
+        writeln!(nodes, "    <node id=\"n{}\">", id).unwrap();
+        writeln!(
+            nodes,
+            "      <data key=\"keys\">{}</data>",
+            escape(&keys.join(","))
+        )
+        .unwrap();
+        writeln!(nodes, "      <data key=\"leaf\">{}</data>", self.leaf).unwrap();
+        writeln!(nodes, "    </node>").unwrap();
+
+        if !self.leaf {
+            for i in 0..=self.n {
+                let c = self.child[i]
+                    .as_ref()
+                    .unwrap()
+                    .write_graphml(nodes, edges, next_id);
+                writeln!(edges, "    <edge source=\"n{}\" target=\"n{}\"/>", id, c).unwrap();
+            }
+        }
+        id
+    }
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Exports the node structure as a GraphML document, with a graph node per tree node
+    /// and an edge from every node to each of its children.
+    /// Every graph node has its keys, comma separated, in the `keys` attribute.
+    pub fn to_graphml(&self) -> String
+    where
+        K: Display,
+    {
+        let mut nodes = String::new();
+        let mut edges = String::new();
+        if let Some(r) = &self.root {
+            r.write_graphml(&mut nodes, &mut edges, &mut 0);
+        }
+
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        out.push_str("  <key id=\"keys\" for=\"node\" attr.name=\"keys\" attr.type=\"string\"/>\n");
+        out.push_str(
+            "  <key id=\"leaf\" for=\"node\" attr.name=\"leaf\" attr.type=\"boolean\"/>\n",
+        );
+        out.push_str("  <graph id=\"btree\" edgedefault=\"directed\">\n");
+        out.push_str(&nodes);
+        out.push_str(&edges);
+        out.push_str("  </graph>\n");
+        out.push_str("</graphml>\n");
+        out
+    }
+}
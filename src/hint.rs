@@ -0,0 +1,63 @@
+use crate::BTree;
+
+/// Cursor remembering the path of the previous `lookup_with_hint`, as the child index taken at every level.
+/// Start with `SearchHint::default()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchHint {
+    path: Vec<usize>,
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Looks up key starting from the path of the previous lookup, and returns the hint for the next one.
+    /// As long as key stays between the separators around the hinted child, that child is entered
+    /// without scanning the keys of the node, so lookups of nearby, e.g. increasing, keys skip most comparisons.
+    /// A hint from before the tree changed is still safe to pass, it just helps less.
+    pub fn lookup_with_hint(&self, key: &K, hint: &SearchHint) -> (Option<(&K, &P)>, SearchHint) {
+        self.ctx.count(|s| &s.searches);
+
+        let mut path = Vec::with_capacity(hint.path.len());
+        let mut node = match &self.root {
+            Some(r) => r,
+            None => return (None, SearchHint { path }),
+        };
+
+        // Follow the hint while the key lies strictly between the separators around the hinted child
+        for &i in hint.path.iter() {
+            if node.leaf || i > node.n {
+                break;
+            }
+
+            let above = i == 0 || node.keys[i - 1].as_ref().unwrap().key < *key;
+            let below = i == node.n || *key < node.keys[i].as_ref().unwrap().key;
+            if !above || !below {
+                break;
+            }
+
+            path.push(i);
+            node = node.child[i].as_ref().unwrap();
+        }
+
+        // Search normally from there
+        loop {
+            let i = node.find_key(key);
+            if i < node.n {
+                let e = node.keys[i].as_ref().unwrap();
+                if e.key == *key {
+                    #[cfg(feature = "access_tracking")]
+                    self.ctx.hit(e.key);
+                    return (Some((&e.key, &e.value)), SearchHint { path });
+                }
+            }
+
+            if node.leaf {
+                return (None, SearchHint { path });
+            }
+            path.push(i);
+            node = node.child[i].as_ref().unwrap();
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::Rc;
+
+use crate::BTree;
+
+/// A `BTree` that stores every distinct value only once.
+/// Values live in a shared pool and the tree entries only hold an index into it.
+#[derive(Debug)]
+pub struct InternedBTree<K, V>
+where
+    K: Eq + Ord + Copy,
+    V: Eq + Hash,
+{
+    tree: BTree<K, usize>,
+    pool: Vec<Rc<V>>,
+    lookup: HashMap<Rc<V>, usize>,
+}
+
+impl<K, V> InternedBTree<K, V>
+where
+    K: Eq + Ord + Copy,
+    V: Eq + Hash,
+{
+    pub fn new(t: usize) -> InternedBTree<K, V> {
+        InternedBTree {
+            tree: BTree::new(t),
+            pool: Vec::new(),
+            lookup: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        // Reuse the pooled copy if the value has been seen before
+        let index = match self.lookup.get(&value) {
+            Some(&i) => i,
+            None => {
+                let value = Rc::new(value);
+                self.pool.push(value.clone());
+                self.lookup.insert(value, self.pool.len() - 1);
+                self.pool.len() - 1
+            }
+        };
+
+        self.tree.insert(key, index);
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let e = self.tree.search(key)?;
+        Some(&self.pool[e.value])
+    }
+
+    /// Returns the amount of distinct values stored in the pool
+    pub fn pool_len(&self) -> usize {
+        self.pool.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InternedBTree;
+
+    #[test]
+    fn shared_value_is_stored_once() {
+        let mut tree = InternedBTree::new(3);
+        for i in 0..1000u32 {
+            tree.insert(i, String::from("shared"));
+        }
+
+        assert_eq!(tree.pool_len(), 1);
+        assert_eq!(tree.get(&500).map(String::as_str), Some("shared"));
+        assert_eq!(tree.get(&5000), None);
+
+        tree.insert(1000, String::from("other"));
+        assert_eq!(tree.pool_len(), 2);
+    }
+}
@@ -0,0 +1,873 @@
+use std::borrow::Borrow;
+use std::ops::{Bound, RangeBounds};
+use std::slice::IterMut;
+
+use crate::{BTree, Entry, Node};
+
+/// In-order iterator over the entries of a `BTree`, or of a key range of it
+pub struct Iter<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    // Path from the root to the next entry, each frame holds the index of the next key to yield in its node
+    stack: Vec<(&'a Node<K, P>, usize)>,
+    // Path from the root to the last entry, each frame holds the number of keys in its node still to yield from the back
+    back: Vec<(&'a Node<K, P>, usize)>,
+    // Entries left between the two ends, which keeps them from crossing
+    remaining: usize,
+    // Nodes pushed onto either path so far
+    visited: usize,
+}
+
+/// Order in which `BTree::scan` visits the entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanDirection {
+    Forward,
+    Reverse,
+}
+
+/// Iterator over a key range of a `BTree` in either direction, see `BTree::scan`
+pub struct Scan<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    iter: Iter<'a, K, P>,
+    dir: ScanDirection,
+}
+
+impl<'a, K, P> Iter<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    fn empty() -> Iter<'a, K, P> {
+        Iter {
+            stack: Vec::new(),
+            back: Vec::new(),
+            remaining: 0,
+            visited: 0,
+        }
+    }
+
+    /// Returns how many nodes the iterator has descended into so far, from either end,
+    /// e.g. to see how much of the tree a traversal or a skip with `nth` touched
+    pub fn nodes_visited(&self) -> usize {
+        self.visited
+    }
+
+    fn push(&mut self, node: &'a Node<K, P>, i: usize) {
+        self.visited += 1;
+        self.stack.push((node, i));
+    }
+
+    fn push_back(&mut self, node: &'a Node<K, P>, i: usize) {
+        self.visited += 1;
+        self.back.push((node, i));
+    }
+
+    // Pushes the path down to the left-most entry of the subtree
+    fn push_left(&mut self, mut node: &'a Node<K, P>) {
+        loop {
+            self.push(node, 0);
+            if node.leaf {
+                return;
+            }
+            node = node.child[0].as_ref().unwrap();
+        }
+    }
+
+    // Pushes the path down to the right-most entry of the subtree onto the back stack
+    fn push_right(&mut self, mut node: &'a Node<K, P>) {
+        loop {
+            self.push_back(node, node.n);
+            if node.leaf {
+                return;
+            }
+            node = node.child[node.n].as_ref().unwrap();
+        }
+    }
+
+    // Pushes the path down to the entry at position n of the subtree
+    fn push_nth(&mut self, mut node: &'a Node<K, P>, mut n: usize) {
+        'descend: loop {
+            if node.leaf {
+                self.push(node, n);
+                return;
+            }
+
+            for i in 0..=node.n {
+                let c = node.child[i].as_ref().unwrap();
+                if n < c.size {
+                    self.push(node, i);
+                    node = c;
+                    continue 'descend;
+                }
+
+                // The key after the child is the entry, or is skipped along with it
+                n -= c.size;
+                if n == 0 {
+                    self.push(node, i);
+                    return;
+                }
+                n -= 1;
+            }
+            return;
+        }
+    }
+
+    // Pushes the path down to the entry at position n from the end of the subtree onto the back stack
+    fn push_nth_back(&mut self, mut node: &'a Node<K, P>, mut n: usize) {
+        'descend: loop {
+            if node.leaf {
+                self.push_back(node, node.n - n);
+                return;
+            }
+
+            for i in (0..=node.n).rev() {
+                let c = node.child[i].as_ref().unwrap();
+                if n < c.size {
+                    self.push_back(node, i);
+                    node = c;
+                    continue 'descend;
+                }
+
+                // The key before the child is the entry, or is skipped along with it
+                n -= c.size;
+                if n == 0 {
+                    self.push_back(node, i);
+                    return;
+                }
+                n -= 1;
+            }
+            return;
+        }
+    }
+
+    // Pushes the path down to the first entry that isn't before the start bound
+    fn seek<Q>(&mut self, mut node: &'a Node<K, P>, start: Bound<&Q>)
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        loop {
+            let mut i = 0;
+            while i < node.n {
+                let k = node.keys[i].as_ref().unwrap().key.borrow();
+                let before = match start {
+                    Bound::Included(s) => k < s,
+                    Bound::Excluded(s) => k <= s,
+                    Bound::Unbounded => false,
+                };
+                if !before {
+                    break;
+                }
+                i += 1;
+            }
+
+            self.push(node, i);
+            if node.leaf {
+                return;
+            }
+            node = node.child[i].as_ref().unwrap();
+        }
+    }
+
+    // Pushes the path down to the last entry that isn't after the end bound onto the back stack
+    fn seek_back<Q>(&mut self, mut node: &'a Node<K, P>, end: Bound<&Q>)
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        loop {
+            let mut i = 0;
+            while i < node.n {
+                let k = node.keys[i].as_ref().unwrap().key.borrow();
+                let after = match end {
+                    Bound::Included(e) => k > e,
+                    Bound::Excluded(e) => k >= e,
+                    Bound::Unbounded => false,
+                };
+                if after {
+                    break;
+                }
+                i += 1;
+            }
+
+            self.push_back(node, i);
+            if node.leaf {
+                return;
+            }
+            node = node.child[i].as_ref().unwrap();
+        }
+    }
+}
+
+impl<'a, K, P> Iterator for Iter<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    type Item = &'a Entry<K, P>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        loop {
+            let (node, i) = self.stack.last_mut()?;
+            let node: &'a Node<K, P> = node;
+
+            if *i < node.n {
+                let e = node.keys[*i].as_ref().unwrap();
+                *i += 1;
+
+                // Everything right of the key comes next
+                if !node.leaf {
+                    let c = node.child[*i].as_ref().unwrap();
+                    self.push_left(c);
+                }
+
+                self.remaining -= 1;
+                return Some(e);
+            }
+
+            self.stack.pop();
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+
+    // Skips whole subtrees by their size instead of walking every entry in them
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if n >= self.remaining {
+            self.remaining = 0;
+            self.stack.clear();
+            self.back.clear();
+            return None;
+        }
+        self.remaining -= n;
+
+        let mut n = n;
+        while n > 0 {
+            let (node, i) = self.stack.last_mut().unwrap();
+            let node: &'a Node<K, P> = node;
+
+            if *i == node.n {
+                self.stack.pop();
+                continue;
+            }
+
+            // Skip the key, then the subtree right of it if the target lies beyond it
+            *i += 1;
+            n -= 1;
+            if !node.leaf {
+                let c = node.child[*i].as_ref().unwrap();
+                if n < c.size {
+                    self.push_nth(c, n);
+                    n = 0;
+                } else {
+                    n -= c.size;
+                }
+            }
+        }
+
+        self.next()
+    }
+}
+
+// size_hint is exact, remaining counts down with every entry yielded from either end
+impl<'a, K, P> ExactSizeIterator for Iter<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+}
+
+impl<'a, K, P> DoubleEndedIterator for Iter<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    // Yields the last entry that hasn't been yielded from either end,
+    // the remaining count tells when the front has caught up with it
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        loop {
+            let (node, i) = self.back.last_mut()?;
+            let node: &'a Node<K, P> = node;
+
+            if *i > 0 {
+                *i -= 1;
+                let e = node.keys[*i].as_ref().unwrap();
+
+                // Everything left of the key comes next
+                if !node.leaf {
+                    let c = node.child[*i].as_ref().unwrap();
+                    self.push_right(c);
+                }
+
+                self.remaining -= 1;
+                return Some(e);
+            }
+
+            self.back.pop();
+        }
+    }
+
+    // Mirrors `nth`, skipping whole subtrees from the back
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        if n >= self.remaining {
+            self.remaining = 0;
+            self.stack.clear();
+            self.back.clear();
+            return None;
+        }
+        self.remaining -= n;
+
+        let mut n = n;
+        while n > 0 {
+            let (node, i) = self.back.last_mut().unwrap();
+            let node: &'a Node<K, P> = node;
+
+            if *i == 0 {
+                self.back.pop();
+                continue;
+            }
+
+            // Skip the key, then the subtree left of it if the target lies before it
+            *i -= 1;
+            n -= 1;
+            if !node.leaf {
+                let c = node.child[*i].as_ref().unwrap();
+                if n < c.size {
+                    self.push_nth_back(c, n);
+                    n = 0;
+                } else {
+                    n -= c.size;
+                }
+            }
+        }
+
+        self.next_back()
+    }
+}
+
+impl<'a, K, P> Scan<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Returns how many nodes the scan has descended into so far, see `Iter::nodes_visited`
+    pub fn nodes_visited(&self) -> usize {
+        self.iter.nodes_visited()
+    }
+}
+
+impl<'a, K, P> Iterator for Scan<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    type Item = &'a Entry<K, P>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.dir {
+            ScanDirection::Forward => self.iter.next(),
+            ScanDirection::Reverse => self.iter.next_back(),
+        }
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        match self.dir {
+            ScanDirection::Forward => self.iter.nth(n),
+            ScanDirection::Reverse => self.iter.nth_back(n),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, K, P> DoubleEndedIterator for Scan<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self.dir {
+            ScanDirection::Forward => self.iter.next_back(),
+            ScanDirection::Reverse => self.iter.next(),
+        }
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        match self.dir {
+            ScanDirection::Forward => self.iter.nth_back(n),
+            ScanDirection::Reverse => self.iter.nth(n),
+        }
+    }
+}
+
+impl<'a, K, P> ExactSizeIterator for Scan<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+}
+
+// Keys and children of a node that are still to be visited
+type MutFrame<'a, K, P> = (
+    IterMut<'a, Option<Entry<K, P>>>,
+    IterMut<'a, Option<Node<K, P>>>,
+);
+
+/// In-order iterator over mutable references to the values of a `BTree`
+pub struct ValuesMut<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    // Every frame splits its node into the keys and the children still to visit,
+    // which keeps the handed out references disjoint
+    stack: Vec<MutFrame<'a, K, P>>,
+}
+
+impl<'a, K, P> ValuesMut<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    // Pushes the path down to the left-most entry of the subtree
+    fn push_left(&mut self, mut node: &'a mut Node<K, P>) {
+        loop {
+            let n = node.n;
+            let keys = node.keys[..n].iter_mut();
+            let mut children = if node.leaf {
+                node.child[..0].iter_mut()
+            } else {
+                node.child[..n + 1].iter_mut()
+            };
+
+            let first = children.next();
+            self.stack.push((keys, children));
+            match first {
+                Some(c) => node = c.as_mut().unwrap(),
+                None => return,
+            }
+        }
+    }
+}
+
+impl<'a, K, P> Iterator for ValuesMut<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    type Item = &'a mut P;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (keys, children) = self.stack.last_mut()?;
+
+            if let Some(e) = keys.next() {
+                let value = &mut e.as_mut().unwrap().value;
+
+                // Everything right of the key comes next
+                if let Some(c) = children.next() {
+                    self.push_left(c.as_mut().unwrap());
+                }
+
+                return Some(value);
+            }
+
+            self.stack.pop();
+        }
+    }
+}
+
+/// Breadth-first iterator over the nodes of a `BTree`, yielding every level from the root down
+pub struct LevelIter<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    level: Vec<&'a Node<K, P>>,
+}
+
+impl<'a, K, P> Iterator for LevelIter<'a, K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    type Item = Vec<&'a Node<K, P>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.level.is_empty() {
+            return None;
+        }
+
+        // The children of this level, in order, make up the next one
+        let mut next = Vec::new();
+        for node in self.level.iter() {
+            if !node.leaf {
+                for i in 0..=node.n {
+                    next.push(node.child[i].as_ref().unwrap());
+                }
+            }
+        }
+
+        Some(std::mem::replace(&mut self.level, next))
+    }
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    pub fn iter(&self) -> Iter<'_, K, P> {
+        let mut iter = Iter::empty();
+        if let Some(r) = &self.root {
+            iter.push_left(r);
+            iter.push_right(r);
+            iter.remaining = r.size;
+        }
+        iter
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &P> {
+        self.iter().map(|e| &e.value)
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, P> {
+        let mut iter = ValuesMut { stack: Vec::new() };
+        if let Some(r) = &mut self.root {
+            iter.push_left(r);
+        }
+        iter
+    }
+
+    pub fn level_iter(&self) -> LevelIter<'_, K, P> {
+        LevelIter {
+            level: self.root.iter().collect(),
+        }
+    }
+
+    /// Iterates over the entries in range in key order
+    pub fn range<Q, R>(&self, range: R) -> Scan<'_, K, P>
+    where
+        K: Borrow<Q>,
+        R: RangeBounds<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.scan(range, ScanDirection::Forward)
+    }
+
+    /// Iterates over the entries in range in reverse key order
+    pub fn range_rev<Q, R>(&self, range: R) -> Scan<'_, K, P>
+    where
+        K: Borrow<Q>,
+        R: RangeBounds<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.scan(range, ScanDirection::Reverse)
+    }
+
+    /// Iterates over the entries in range in the given direction.
+    /// Either way the iterator can also be consumed from its other end.
+    pub fn scan<Q, R>(&self, range: R, dir: ScanDirection) -> Scan<'_, K, P>
+    where
+        K: Borrow<Q>,
+        R: RangeBounds<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut iter = Iter::empty();
+        if let Some(r) = &self.root {
+            iter.seek(r, range.start_bound());
+            iter.seek_back(r, range.end_bound());
+            iter.remaining = self.range_count(&range);
+        }
+        Scan { iter, dir }
+    }
+
+    /// Iterates over the entries whose key starts with prefix, for keys that can be viewed as bytes
+    pub fn prefix_range(&self, prefix: &[u8]) -> impl Iterator<Item = &Entry<K, P>>
+    where
+        K: Borrow<[u8]>,
+    {
+        // The first key past the prefix is found by incrementing the last byte that can be incremented,
+        // if every byte is 0xFF there is no upper bound
+        let mut successor = prefix.to_vec();
+        while successor.last() == Some(&0xFF) {
+            successor.pop();
+        }
+        let end = match successor.last_mut() {
+            Some(b) => {
+                *b += 1;
+                Bound::Excluded(&successor[..])
+            }
+            None => Bound::Unbounded,
+        };
+
+        self.range::<[u8], _>((Bound::Included(prefix), end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{allocations, lcg};
+    use crate::{BTree, ScanDirection};
+
+    #[test]
+    fn prefix_range_returns_only_prefixed_keys() {
+        let mut tree: BTree<[u8; 3], u32> = BTree::new(2);
+        let mut all = Vec::new();
+        for a in [0u8, 1, 0xFE, 0xFF].iter() {
+            for b in [0u8, 5, 0xFF].iter() {
+                for c in 0..4u8 {
+                    tree.insert([*a, *b, c], 0);
+                    all.push([*a, *b, c]);
+                }
+            }
+        }
+        all.sort();
+
+        // The 0xFF prefixes have no successor, or one that carries into an earlier byte
+        let prefixes: [&[u8]; 8] = [
+            &[],
+            &[1],
+            &[1, 5, 2],
+            &[2],
+            &[0xFF],
+            &[0xFF, 0xFF],
+            &[0xFE, 0xFF],
+            &[0xFF, 0xFF, 3],
+        ];
+        for prefix in prefixes.iter() {
+            let expected: Vec<[u8; 3]> = all
+                .iter()
+                .copied()
+                .filter(|k| k.starts_with(prefix))
+                .collect();
+            let found: Vec<[u8; 3]> = tree.prefix_range(prefix).map(|e| e.key).collect();
+            assert_eq!(found, expected, "prefix {:?}", prefix);
+        }
+    }
+
+    #[test]
+    fn values_mut_scales_every_value() {
+        let mut tree = BTree::new(2);
+        let mut seed = 158;
+        for _ in 0..500 {
+            let k = lcg(&mut seed) % 10_000;
+            tree.insert(k, k);
+        }
+
+        for v in tree.values_mut() {
+            *v *= 3;
+        }
+
+        let expected: Vec<u64> = tree.iter().map(|e| e.key * 3).collect();
+        assert_eq!(tree.values().copied().collect::<Vec<_>>(), expected);
+        assert_eq!(tree.values_mut().count(), tree.len());
+    }
+
+    #[test]
+    fn nth_skips_subtrees_instead_of_walking_them() {
+        let mut tree = BTree::new(3);
+        for i in 0..100_000u32 {
+            tree.insert(i, i);
+        }
+
+        let mut naive = tree.iter();
+        for _ in 0..5000 {
+            naive.next();
+        }
+        assert_eq!(naive.next().map(|e| e.key), Some(5000));
+
+        let mut skipping = tree.iter();
+        assert_eq!(skipping.nth(5000).map(|e| e.key), Some(5000));
+        assert!(skipping.nodes_visited() * 20 < naive.nodes_visited());
+        assert_eq!(skipping.next().map(|e| e.key), Some(5001));
+
+        let mut skipping = tree.iter();
+        assert_eq!(skipping.nth_back(5000).map(|e| e.key), Some(94_999));
+        assert!(skipping.nodes_visited() * 20 < naive.nodes_visited());
+        assert_eq!(skipping.next_back().map(|e| e.key), Some(94_998));
+    }
+
+    #[test]
+    fn scan_nth_skips_in_either_direction() {
+        let mut tree = BTree::new(3);
+        for i in 0..100_000u32 {
+            tree.insert(i, i);
+        }
+
+        let mut naive = tree.range(10_000..90_000);
+        for _ in 0..5000 {
+            naive.next();
+        }
+        assert_eq!(naive.next().map(|e| e.key), Some(15_000));
+
+        let mut forward = tree.range(10_000..90_000);
+        assert_eq!(forward.nth(5000).map(|e| e.key), Some(15_000));
+        assert!(forward.nodes_visited() * 20 < naive.nodes_visited());
+        assert_eq!(forward.next().map(|e| e.key), Some(15_001));
+        assert_eq!(forward.nth_back(5000).map(|e| e.key), Some(84_999));
+
+        let mut reverse = tree.range_rev(10_000..90_000);
+        assert_eq!(reverse.nth(5000).map(|e| e.key), Some(84_999));
+        assert!(reverse.nodes_visited() * 20 < naive.nodes_visited());
+        assert_eq!(reverse.next().map(|e| e.key), Some(84_998));
+        assert_eq!(reverse.nth_back(5000).map(|e| e.key), Some(15_000));
+        assert_eq!(reverse.len(), 80_000 - 10_003);
+    }
+
+    #[test]
+    fn reverse_scans_mirror_forward_scans() {
+        use std::ops::Bound::{self, Excluded, Included, Unbounded};
+        use std::ops::RangeBounds;
+
+        let mut seed = 190;
+        for t in 2..5 {
+            let mut tree = BTree::new(t);
+            for _ in 0..500 {
+                tree.insert((lcg(&mut seed) % 400) as u32, 0u8);
+            }
+
+            let bound = |seed: &mut u64| {
+                let k = (lcg(seed) % 420) as u32;
+                match lcg(seed) % 3 {
+                    0 => Included(k),
+                    1 => Excluded(k),
+                    _ => Unbounded,
+                }
+            };
+            for _ in 0..200 {
+                let range: (Bound<u32>, Bound<u32>) = (bound(&mut seed), bound(&mut seed));
+                let forward: Vec<u32> = tree
+                    .scan(range, ScanDirection::Forward)
+                    .map(|e| e.key)
+                    .collect();
+                let mut reverse: Vec<u32> = tree
+                    .scan(range, ScanDirection::Reverse)
+                    .map(|e| e.key)
+                    .collect();
+                let rev: Vec<u32> = tree.range_rev(range).map(|e| e.key).collect();
+
+                assert_eq!(rev, reverse, "t = {}, range {:?}", t, range);
+                reverse.reverse();
+                assert_eq!(reverse, forward, "t = {}, range {:?}", t, range);
+
+                let expected: Vec<u32> = tree
+                    .iter()
+                    .map(|e| e.key)
+                    .filter(|k| range.contains(k))
+                    .collect();
+                assert_eq!(forward, expected, "t = {}, range {:?}", t, range);
+            }
+
+            // The fully unbounded scans cover the whole tree
+            let all: Vec<u32> = tree.iter().map(|e| e.key).collect();
+            let mut reverse: Vec<u32> = tree.range_rev(..).map(|e| e.key).collect();
+            reverse.reverse();
+            assert_eq!(reverse, all);
+        }
+    }
+
+    #[test]
+    fn nth_from_both_ends_matches_a_vec() {
+        let mut tree = BTree::new(2);
+        let mut seed = 176;
+        for _ in 0..3000 {
+            tree.insert(lcg(&mut seed) % 1000, 0u8);
+        }
+        let keys: Vec<u64> = tree.iter().map(|e| e.key).collect();
+
+        for round in 0..200 {
+            let mut iter = tree.iter();
+            let mut model = keys.iter().copied();
+
+            for _ in 0..20 {
+                let n = (lcg(&mut seed) % 300) as usize;
+                let (found, expected) = match lcg(&mut seed) % 4 {
+                    0 => (iter.nth(n), model.nth(n)),
+                    1 => (iter.nth_back(n), model.nth_back(n)),
+                    2 => (iter.next(), model.next()),
+                    _ => (iter.next_back(), model.next_back()),
+                };
+                assert_eq!(found.map(|e| e.key), expected, "round {}", round);
+                assert_eq!(iter.len(), model.clone().count());
+            }
+        }
+    }
+
+    #[test]
+    fn front_and_back_meet_in_the_middle() {
+        let mut tree = BTree::new(3);
+        for i in 0..1000u32 {
+            tree.insert(i, i);
+        }
+
+        let mut iter = tree.iter();
+        let (mut front, mut back) = (Vec::new(), Vec::new());
+        while let Some(e) = iter.next() {
+            front.push(e.key);
+            // Take two from the back for every one from the front, so they meet off the middle
+            for _ in 0..2 {
+                if let Some(e) = iter.next_back() {
+                    back.push(e.key);
+                }
+            }
+        }
+
+        assert_eq!(front.len() + back.len(), 1000);
+        back.reverse();
+        front.extend(back);
+        assert_eq!(front, (0..1000).collect::<Vec<u32>>());
+        assert!(iter.next().is_none());
+        assert!(iter.next_back().is_none());
+        assert_eq!(
+            tree.iter().rev().map(|e| e.key).collect::<Vec<u32>>(),
+            (0..1000).rev().collect::<Vec<u32>>()
+        );
+    }
+
+    #[test]
+    fn len_counts_down_as_entries_are_taken() {
+        let mut tree = BTree::new(3);
+        for i in 0..1000u32 {
+            tree.insert(i, i);
+        }
+
+        let mut iter = tree.iter();
+        assert_eq!(iter.len(), tree.len());
+        for taken in 1..=1000 {
+            if taken % 2 == 0 {
+                iter.next();
+            } else {
+                iter.next_back();
+            }
+            assert_eq!(iter.len(), 1000 - taken);
+            assert_eq!(iter.size_hint(), (1000 - taken, Some(1000 - taken)));
+        }
+        assert!(iter.next().is_none());
+        assert_eq!(iter.len(), 0);
+
+        // Collecting allocates the vec once, at the right size
+        let iter = tree.iter();
+        let before = allocations();
+        let entries: Vec<_> = iter.collect();
+        assert_eq!(allocations() - before, 1);
+        assert_eq!(entries.capacity(), 1000);
+    }
+}
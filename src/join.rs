@@ -0,0 +1,318 @@
+use crate::{Context, Entry, Node};
+
+// A subtree cut out of a tree or joined from pieces, with its height in levels, 0 if it's empty
+pub type Piece<K, P> = (Option<Node<K, P>>, usize);
+
+impl<K, P> Node<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    // Levels in this subtree, a lone leaf being 1
+    pub fn height(&self) -> usize {
+        let mut height = 1;
+        let mut node = self;
+        while !node.leaf {
+            node = node.child[0].as_ref().unwrap();
+            height += 1;
+        }
+        height
+    }
+
+    // Splits the subtree, of the given height, in two: the keys for which goes_left holds, which have to come first,
+    // and the rest. Only the nodes along the path to the split point are taken apart, the pieces left and right of
+    // that path are joined back together on the way up. Both halves are valid trees.
+    pub fn split_by<F>(
+        mut self,
+        height: usize,
+        goes_left: &F,
+        ctx: &mut Context<K, P>,
+    ) -> (Piece<K, P>, Piece<K, P>)
+    where
+        F: Fn(&K) -> bool,
+    {
+        let n = self.n;
+        let i = self.iter_keys().take_while(|k| goes_left(k)).count();
+
+        if self.leaf {
+            let mut right = ctx.pool.take(self.t, true);
+            for j in i..n {
+                right.keys[j - i] = self.keys[j].take();
+            }
+            right.n = n - i;
+            right.size = right.n;
+            self.n = i;
+            self.size = i;
+            return (piece(self, 1, ctx), piece(right, 1, ctx));
+        }
+
+        let c = self.child[i].take().unwrap();
+        let (child_left, child_right) = c.split_by(height - 1, goes_left, ctx);
+
+        // The keys and children right of the path make up one piece, joined to what the child left on its right
+        let right = if i == n {
+            child_right
+        } else {
+            let separator = self.keys[i].take().unwrap();
+            let mut r = ctx.pool.take(self.t, false);
+            for j in i + 1..n {
+                r.keys[j - i - 1] = self.keys[j].take();
+            }
+            for j in i + 1..=n {
+                r.child[j - i - 1] = self.child[j].take();
+            }
+            r.n = n - i - 1;
+            r.resize();
+            let r = piece(r, height, ctx);
+            join(self.t, child_right, separator, r, ctx)
+        };
+
+        // And this node, cut down to the keys and children left of the path, makes up the other
+        let left = if i == 0 {
+            self.n = 0;
+            ctx.pool.give(self);
+            child_left
+        } else {
+            let separator = self.keys[i - 1].take().unwrap();
+            self.n = i - 1;
+            self.resize();
+            let l_t = self.t;
+            let l = piece(self, height, ctx);
+            join(l_t, l, separator, child_left, ctx)
+        };
+
+        (left, right)
+    }
+
+    // Removes and returns the first entry, filling up the left-most path on the way down like `remove` does
+    pub fn pop_first(&mut self, ctx: &mut Context<K, P>) -> Entry<K, P> {
+        self.size -= 1;
+        if self.leaf {
+            let e = self.keys[0].take();
+            for j in 1..self.n {
+                self.keys[j - 1] = self.keys[j].take();
+            }
+            self.n -= 1;
+            return e.unwrap();
+        }
+
+        if self.child[0].as_ref().unwrap().n < self.t {
+            self.fill(0, ctx);
+        }
+        self.child[0].as_mut().unwrap().pop_first(ctx)
+    }
+
+    // Recomputes the size of this node from its keys and children
+    fn resize(&mut self) {
+        self.size = self.n;
+        if !self.leaf {
+            for j in 0..=self.n {
+                self.size += self.child[j].as_ref().unwrap().size;
+            }
+        }
+    }
+
+    // Appends separator and the subtree right, of height h, to the right-most node of this subtree
+    // whose children are that high, keeping the full nodes on the way down out of the way by splitting them
+    fn push_last(
+        &mut self,
+        height: usize,
+        separator: Entry<K, P>,
+        right: Piece<K, P>,
+        ctx: &mut Context<K, P>,
+    ) {
+        let (right, h) = right;
+        self.size += 1 + right.as_ref().map_or(0, |r| r.size);
+
+        if height > h + 1 {
+            let last = self.n;
+            if self.child[last].as_ref().unwrap().n == 2 * self.t - 1 {
+                self.split_nodes(last, last, ctx);
+            }
+            let last = self.n;
+            let c = self.child[last].as_mut().unwrap();
+            return c.push_last(height - 1, separator, (right, h), ctx);
+        }
+
+        let n = self.n;
+        self.keys[n] = Some(separator);
+        self.n += 1;
+        if !self.leaf {
+            self.child[n + 1] = right;
+            self.fill_from_prev(n + 1, ctx);
+        }
+    }
+
+    // Mirrors `push_last`, prepending the subtree left, of height h, and separator to the left-most node
+    fn push_first(
+        &mut self,
+        height: usize,
+        left: Piece<K, P>,
+        separator: Entry<K, P>,
+        ctx: &mut Context<K, P>,
+    ) {
+        let (left, h) = left;
+        self.size += 1 + left.as_ref().map_or(0, |l| l.size);
+
+        if height > h + 1 {
+            if self.child[0].as_ref().unwrap().n == 2 * self.t - 1 {
+                self.split_nodes(0, 0, ctx);
+            }
+            let c = self.child[0].as_mut().unwrap();
+            return c.push_first(height - 1, (left, h), separator, ctx);
+        }
+
+        let n = self.n;
+        for j in (0..n).rev() {
+            self.keys[j + 1] = self.keys[j].take();
+        }
+        self.keys[0] = Some(separator);
+        if !self.leaf {
+            for j in (0..=n).rev() {
+                self.child[j + 1] = self.child[j].take();
+            }
+            self.child[0] = left;
+        }
+        self.n += 1;
+        if !self.leaf {
+            self.fill_from_next(0, ctx);
+        }
+    }
+
+    // Brings the child at idx, the root of a tree that was just attached, up to t - 1 keys
+    // by merging it into its left sibling or borrowing from it
+    fn fill_from_prev(&mut self, idx: usize, ctx: &mut Context<K, P>) {
+        let n = self.child[idx].as_ref().unwrap().n;
+        if n >= self.t - 1 {
+            return;
+        }
+        if n + self.child[idx - 1].as_ref().unwrap().n < 2 * self.t - 1 {
+            self.merge(idx - 1, ctx);
+            return;
+        }
+        while self.child[idx].as_ref().unwrap().n < self.t - 1 {
+            self.borrow_from_prev(idx);
+        }
+    }
+
+    // Like `fill_from_prev`, with the right sibling
+    fn fill_from_next(&mut self, idx: usize, ctx: &mut Context<K, P>) {
+        let n = self.child[idx].as_ref().unwrap().n;
+        if n >= self.t - 1 {
+            return;
+        }
+        if n + self.child[idx + 1].as_ref().unwrap().n < 2 * self.t - 1 {
+            self.merge(idx, ctx);
+            return;
+        }
+        while self.child[idx].as_ref().unwrap().n < self.t - 1 {
+            self.borrow_from_next(idx);
+        }
+    }
+}
+
+// Wraps a node that was cut down as a piece, a node left without keys is replaced by its only child
+fn piece<K, P>(mut node: Node<K, P>, height: usize, ctx: &mut Context<K, P>) -> Piece<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    if node.n > 0 {
+        return (Some(node), height);
+    }
+
+    let c = node.child[0].take();
+    ctx.pool.give(node);
+    (c, height - 1)
+}
+
+// Joins two trees of degree t around separator, every key in left coming before it and every key in right after it.
+// Only the nodes along the edge of the higher tree, down to the height of the lower one, are touched.
+pub fn join<K, P>(
+    t: usize,
+    left: Piece<K, P>,
+    separator: Entry<K, P>,
+    right: Piece<K, P>,
+    ctx: &mut Context<K, P>,
+) -> Piece<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    let ((left, hl), (right, hr)) = (left, right);
+
+    if hl == 0 && hr == 0 {
+        let mut leaf = ctx.pool.take(t, true);
+        leaf.keys[0] = Some(separator);
+        leaf.n = 1;
+        leaf.size = 1;
+        return (Some(leaf), 1);
+    }
+
+    if hl > hr {
+        let (mut l, h) = grow_if_full(left.unwrap(), hl, ctx);
+        l.push_last(h, separator, (right, hr), ctx);
+        return (Some(l), h);
+    }
+    if hl < hr {
+        let (mut r, h) = grow_if_full(right.unwrap(), hr, ctx);
+        r.push_first(h, (left, hl), separator, ctx);
+        return (Some(r), h);
+    }
+
+    let (mut l, mut r) = (left.unwrap(), right.unwrap());
+    if l.n + r.n < 2 * t - 1 {
+        // Both fit in a single node
+        let m = l.n;
+        l.keys[m] = Some(separator);
+        for j in 0..r.n {
+            l.keys[m + 1 + j] = r.keys[j].take();
+        }
+        if !l.leaf {
+            for j in 0..=r.n {
+                l.child[m + 1 + j] = r.child[j].take();
+            }
+        }
+        l.n += r.n + 1;
+        l.size += r.size + 1;
+        r.n = 0;
+        ctx.pool.give(r);
+        return (Some(l), hl);
+    }
+
+    // A new root over the two, evening them out so neither is underfull
+    let mut root = ctx.pool.take(t, false);
+    root.size = l.size + r.size + 1;
+    root.keys[0] = Some(separator);
+    root.child[0] = Some(l);
+    root.child[1] = Some(r);
+    root.n = 1;
+    while root.child[0].as_ref().unwrap().n < t - 1 {
+        root.borrow_from_next(0);
+    }
+    while root.child[1].as_ref().unwrap().n < t - 1 {
+        root.borrow_from_prev(1);
+    }
+    (Some(root), hl + 1)
+}
+
+// Splits a full root under a new one, so a key can be added below it
+fn grow_if_full<K, P>(
+    root: Node<K, P>,
+    height: usize,
+    ctx: &mut Context<K, P>,
+) -> (Node<K, P>, usize)
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    if root.n < 2 * root.t - 1 {
+        return (root, height);
+    }
+
+    let mut s = ctx.pool.take(root.t, false);
+    s.size = root.size;
+    s.child[0] = Some(root);
+    s.split_nodes(0, 0, ctx);
+    (s, height + 1)
+}
@@ -0,0 +1,50 @@
+use crate::BTree;
+
+/// How full the nodes on one level of a `BTree` are, see `BTree::level_stats`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelStats {
+    /// Depth of the level, the root is at level 0
+    pub level: usize,
+    pub node_count: usize,
+    pub total_keys: usize,
+    pub min_keys: usize,
+    pub max_keys: usize,
+    pub avg_keys: f64,
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Returns the key counts of the nodes on every level, from the root down.
+    /// A well balanced tree has about the same `avg_keys` on every level.
+    pub fn level_stats(&self) -> Vec<LevelStats> {
+        self.level_iter()
+            .enumerate()
+            .map(|(level, nodes)| {
+                let total_keys = nodes.iter().map(|n| n.n).sum();
+                LevelStats {
+                    level,
+                    node_count: nodes.len(),
+                    total_keys,
+                    min_keys: nodes.iter().map(|n| n.n).min().unwrap(),
+                    max_keys: nodes.iter().map(|n| n.n).max().unwrap(),
+                    avg_keys: total_keys as f64 / nodes.len() as f64,
+                }
+            })
+            .collect()
+    }
+
+    /// Counts the nodes by how many keys they hold, `histogram[i]` being the number of nodes with i keys.
+    /// Shows how well splits and merges keep the nodes filled, most nodes of a healthy tree hold around `1.5t` keys.
+    pub fn node_histogram(&self) -> Vec<usize> {
+        let mut histogram = vec![0; 2 * self.t];
+        for level in self.level_iter() {
+            for node in level {
+                histogram[node.n] += 1;
+            }
+        }
+        histogram
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{BTree, Node};
+
+// Layout of serialize_levelorder, a generalized heap layout for a tree where every node has up to 2t children:
+//
+//   node j has its 2t - 1 key slots at [j * (2t - 1), (j + 1) * (2t - 1)),
+//   and child c, for c in 0..2t, is node j * 2t + 1 + c
+//
+// The root is node 0, so level l holds the (2t)^l nodes following the levels above it.
+// Slots of missing keys, and of nodes that a full tree of the same height would have, are None.
+
+impl<K, P> Node<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    // Writes this subtree to out as node j of the layout
+    fn place_levelorder(&self, j: usize, out: &mut [Option<(K, P)>]) {
+        let base = j * (2 * self.t - 1);
+        for i in 0..self.n {
+            out[base + i] = Some(self.keys[i].as_ref().unwrap().into_pair());
+        }
+
+        if !self.leaf {
+            for c in 0..=self.n {
+                self.child[c]
+                    .as_ref()
+                    .unwrap()
+                    .place_levelorder(j * 2 * self.t + 1 + c, out);
+            }
+        }
+    }
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Lays the entries out in an array the way a binary heap is, generalized to 2t children per node:
+    /// node j holds slots `j * (2t - 1)..(j + 1) * (2t - 1)` and its child c is node `j * 2t + 1 + c`.
+    /// Level l takes up a block of `(2t)^l` nodes, and empty slots are `None`.
+    /// The array is sized for a full tree of the same height, so it grows with `(2t)^height`.
+    pub fn serialize_levelorder(&self) -> Vec<Option<(K, P)>> {
+        let root = match &self.root {
+            Some(r) => r,
+            None => return Vec::new(),
+        };
+
+        let branching = 2 * self.t;
+        let mut nodes: usize = 0;
+        let mut level: usize = 1;
+        for _ in 0..self.level_iter().count() {
+            nodes = nodes
+                .checked_add(level)
+                .expect("Level-order layout doesn't fit in memory");
+            level = level.saturating_mul(branching);
+        }
+
+        let slots = nodes
+            .checked_mul(branching - 1)
+            .expect("Level-order layout doesn't fit in memory");
+        let mut out = vec![None; slots];
+        root.place_levelorder(0, &mut out);
+        out
+    }
+}
@@ -0,0 +1,1649 @@
+use std::borrow::{Borrow, Cow};
+use std::cmp::{Eq, Ord, Ordering};
+use std::fmt;
+use std::ops::{Add, Bound, RangeBounds};
+
+#[cfg(feature = "access_tracking")]
+mod access;
+mod aggregate;
+pub mod bplus;
+pub mod bst;
+mod bulk;
+mod compact;
+pub mod context;
+#[cfg(feature = "csv")]
+mod csv_load;
+pub mod cursor;
+pub mod diff;
+pub mod entry;
+pub mod error;
+pub mod fixed;
+pub mod frozen;
+mod graphml;
+pub mod hint;
+pub mod interned;
+pub mod iter;
+mod join;
+pub mod level_stats;
+mod levelorder;
+pub mod mmap;
+pub mod observer;
+mod order;
+pub mod pool;
+pub mod range_min;
+pub mod repair;
+#[cfg(test)]
+mod tests;
+mod validate;
+pub mod visitor;
+
+pub use bplus::{BPlusRange, BPlusTree};
+pub use bst::BSTNode;
+pub use context::{Context, Event, OpStats, SplitStrategy};
+pub use cursor::Cursor;
+pub use diff::{Change, Diff};
+pub use entry::{BtreeEntry, OccupiedEntry, VacantEntry};
+#[cfg(feature = "csv")]
+pub use error::CsvError;
+pub use error::{BTreeError, BudgetExceeded, DecodeError, DuplicateKeyError, VerifyError};
+pub use fixed::FixedSized;
+pub use frozen::FrozenIndex;
+pub use hint::SearchHint;
+pub use interned::InternedBTree;
+pub use iter::{Iter, LevelIter, Scan, ScanDirection, ValuesMut};
+pub use level_stats::LevelStats;
+pub use mmap::MappedBTree;
+pub use observer::{ObservedBTree, Observer};
+pub use pool::NodePool;
+pub use range_min::RangeMinBTree;
+pub use repair::RepairReport;
+pub use visitor::Visitor;
+
+// Returns true if key sorts before every key in the range
+fn before_start<K: Ord, R: RangeBounds<K>>(range: &R, key: &K) -> bool {
+    match range.start_bound() {
+        Bound::Included(s) => key < s,
+        Bound::Excluded(s) => key <= s,
+        Bound::Unbounded => false,
+    }
+}
+
+// Returns true if key sorts after every key in the range
+fn after_end<K: Ord, R: RangeBounds<K>>(range: &R, key: &K) -> bool {
+    match range.end_bound() {
+        Bound::Included(e) => key > e,
+        Bound::Excluded(e) => key >= e,
+        Bound::Unbounded => false,
+    }
+}
+
+// Returns true if every key between lo and hi is in the range, a missing bound being unbounded.
+// Both bounds count as included, as duplicates of a separator can sit on either side of it.
+fn covers<K: Ord, R: RangeBounds<K>>(range: &R, lo: Option<&K>, hi: Option<&K>) -> bool {
+    let start = match (range.start_bound(), lo) {
+        (Bound::Unbounded, _) => true,
+        (Bound::Included(s), Some(l)) => l >= s,
+        (Bound::Excluded(s), Some(l)) => l > s,
+        _ => false,
+    };
+    let end = match (range.end_bound(), hi) {
+        (Bound::Unbounded, _) => true,
+        (Bound::Included(e), Some(h)) => h <= e,
+        (Bound::Excluded(e), Some(h)) => h < e,
+        _ => false,
+    };
+    start && end
+}
+
+// Widens the cached smallest and largest key to include key
+fn widen_extremes<K: Ord + Copy>(min: &mut Option<K>, max: &mut Option<K>, key: K) {
+    if min.is_none_or(|m| key < m) {
+        *min = Some(key);
+    }
+    if max.is_none_or(|m| key > m) {
+        *max = Some(key);
+    }
+}
+
+// Leafs are always on the same level
+// The tree grows upward, by splitting nodes
+
+#[derive(Debug, Clone, Copy)]
+pub struct Entry<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    key: K,
+    value: P,
+}
+
+impl<K, P> Entry<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    pub fn new(key: K, value: P) -> Entry<K, P> {
+        Entry { key, value }
+    }
+
+    pub fn get_key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn value(&self) -> &P {
+        &self.value
+    }
+
+    pub fn value_mut(&mut self) -> &mut P {
+        &mut self.value
+    }
+
+    pub fn into_pair(self) -> (K, P) {
+        (self.key, self.value)
+    }
+}
+
+// Key slots of a node. With the smallvec feature, nodes of degree 2 keep their keys inline instead of on the heap.
+#[cfg(not(feature = "smallvec"))]
+type KeySlots<K, P> = Box<[Option<Entry<K, P>>]>;
+#[cfg(feature = "smallvec")]
+type KeySlots<K, P> = smallvec::SmallVec<[Option<Entry<K, P>>; 3]>;
+
+// Floor and ceiling entries of a key, see BTree::bracket
+type Bracket<'a, K, P> = (Option<&'a Entry<K, P>>, Option<&'a Entry<K, P>>);
+
+#[derive(Debug, Clone)]
+pub struct Node<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    pub t: usize,
+    pub n: usize,
+    // Number of entries in the subtree rooted at this node
+    pub size: usize,
+    pub leaf: bool,
+    pub keys: KeySlots<K, P>,
+    pub child: Box<[Option<Node<K, P>>]>,
+}
+
+impl<K, P> Node<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    pub fn new(t: usize, leaf: bool) -> Node<K, P> {
+        // Initialize child
+        let child = (0..2 * t)
+            .map(|_| None)
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
+
+        // Initialize keys
+        #[cfg(not(feature = "smallvec"))]
+        let keys = vec![None; 2 * t - 1].into_boxed_slice();
+        #[cfg(feature = "smallvec")]
+        let keys = smallvec::SmallVec::from_elem(None, 2 * t - 1);
+
+        Node {
+            t,
+            n: 0,
+            size: 0,
+            leaf,
+            keys,
+            child,
+        }
+    }
+
+    pub fn traverse<'a>(&'a self, t: &mut Vec<&'a Entry<K, P>>) {
+        for i in 0..self.n {
+            let k = self.keys[i].as_ref().unwrap();
+            t.push(k);
+        }
+
+        if !self.leaf {
+            for i in 0..self.n + 1 {
+                let c = self.child[i].as_ref().unwrap();
+                c.traverse(t);
+            }
+        }
+    }
+
+    pub fn search(&self, key: &K, force_linear: bool) -> Option<Entry<K, P>> {
+        let mut i = 0;
+        if !force_linear && self.n > 512 {
+            match self.binary_search_keys(key) {
+                Ok(found) => return self.keys[found],
+                Err(next) => i = next,
+            }
+        } else {
+            // A single three-way comparison per key decides whether to move on, stop or descend
+            while i < self.n {
+                match self.keys[i].as_ref().unwrap().get_key().cmp(key) {
+                    Ordering::Less => i += 1,
+                    Ordering::Equal => return self.keys[i],
+                    Ordering::Greater => break,
+                }
+            }
+        }
+
+        if self.leaf {
+            return None;
+        }
+
+        self.child[i].as_ref().unwrap().search(key, force_linear)
+    }
+
+    pub fn find<Q>(&self, key: &Q) -> Option<&Entry<K, P>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut node = self;
+        loop {
+            let mut i = 0;
+            while i < node.n {
+                match node.keys[i].as_ref().unwrap().key.borrow().cmp(key) {
+                    Ordering::Less => i += 1,
+                    Ordering::Equal => return node.keys[i].as_ref(),
+                    Ordering::Greater => break,
+                }
+            }
+
+            if node.leaf {
+                return None;
+            }
+            node = node.child[i].as_ref().unwrap();
+        }
+    }
+
+    // Returns the entries with the largest key not above key and the smallest key not below it.
+    // Each key passed on the way down is closer than the ones seen before, so a single descent finds both.
+    pub fn bracket(&self, key: &K) -> Bracket<'_, K, P> {
+        let mut floor = None;
+        let mut ceiling = None;
+        let mut node = self;
+        loop {
+            let mut i = 0;
+            while i < node.n {
+                let e = node.keys[i].as_ref().unwrap();
+                match e.key.cmp(key) {
+                    Ordering::Less => {
+                        floor = Some(e);
+                        i += 1;
+                    }
+                    Ordering::Equal => return (Some(e), Some(e)),
+                    Ordering::Greater => {
+                        ceiling = Some(e);
+                        break;
+                    }
+                }
+            }
+
+            if node.leaf {
+                return (floor, ceiling);
+            }
+            node = node.child[i].as_ref().unwrap();
+        }
+    }
+
+    pub fn find_mut<Q>(&mut self, key: &Q) -> Option<&mut Entry<K, P>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut i = 0;
+        while i < self.n {
+            match self.keys[i].as_ref().unwrap().key.borrow().cmp(key) {
+                Ordering::Less => i += 1,
+                Ordering::Equal => return self.keys[i].as_mut(),
+                Ordering::Greater => break,
+            }
+        }
+
+        if self.leaf {
+            return None;
+        }
+        self.child[i].as_mut().unwrap().find_mut(key)
+    }
+
+    // Returns Ok with the index of key if it's in this node,
+    // otherwise Err with the index of the child that would hold it
+    pub fn binary_search_keys(&self, key: &K) -> Result<usize, usize> {
+        let mut low = 0;
+        let mut high = self.n;
+
+        while low < high {
+            let mid = low + ((high - low) / 2);
+
+            match self.keys[mid].as_ref().unwrap().get_key().cmp(key) {
+                Ordering::Equal => return Ok(mid),
+                Ordering::Greater => high = mid,
+                Ordering::Less => low = mid + 1,
+            }
+        }
+
+        Err(low)
+    }
+
+    // Inserts into this subtree, which must not be full, and returns the in-order position the entry ended up at
+    pub fn insert_non_full(&mut self, key: K, pointer: P, ctx: &mut Context<K, P>) -> usize {
+        let mut i: isize = (self.n - 1) as isize;
+        self.size += 1;
+
+        // Insert into leaf if node is a leaf
+        if self.leaf {
+            while i >= 0 && self.keys[i as usize].as_ref().unwrap().get_key() > &key {
+                self.keys[(i + 1) as usize] = self.keys[i as usize].take();
+                i -= 1;
+            }
+
+            self.keys[(i + 1) as usize] = Some(Entry::new(key, pointer));
+            self.n += 1;
+            (i + 1) as usize
+        } else {
+            while i >= 0 && self.keys[i as usize].as_ref().unwrap().get_key() > &key {
+                i -= 1;
+            }
+
+            if self.child[(i + 1) as usize].as_ref().unwrap().n == 2 * self.t - 1 {
+                self.make_room((i + 1) as usize, ctx);
+
+                // The separators around the child changed, so pick the child again
+                i = (self.n - 1) as isize;
+                while i >= 0 && self.keys[i as usize].as_ref().unwrap().get_key() > &key {
+                    i -= 1;
+                }
+            }
+
+            // Every child left of the one we descend into, and the key after each of them, comes first
+            let c = (i + 1) as usize;
+            let mut rank = c;
+            for j in 0..c {
+                rank += self.child[j].as_ref().unwrap().size;
+            }
+            rank + self.child[c]
+                .as_mut()
+                .unwrap()
+                .insert_non_full(key, pointer, ctx)
+        }
+    }
+
+    pub fn split_nodes(&mut self, pos: usize, child_index: usize, ctx: &mut Context<K, P>) {
+        let y = self.child[child_index].as_mut().unwrap();
+
+        // Create second node to take a piece of Ys keys
+        let mut z = ctx.pool.take(self.t, y.leaf);
+        z.n = y.t - 1;
+
+        // Move [t - 1] keys from y to z, as we are splitting
+        let mut j = 0;
+        while j < self.t - 1 {
+            z.keys[j] = y.keys[j + self.t].take();
+            z.size += 1;
+            j += 1;
+        }
+
+        // When splitting, if the node is not a leaf, it has to move [t] children from y to z
+        if !y.leaf {
+            let mut j = 0;
+            while j < self.t {
+                z.child[j] = y.child[j + self.t].take();
+                z.size += z.child[j].as_ref().unwrap().size;
+                j += 1;
+            }
+        }
+
+        // Y keeps what's left, minus the key moving up to the parent
+        y.n = self.t - 1;
+        y.size -= z.size + 1;
+        let c = y.keys[self.t - 1].take();
+        ctx.record(Event::Split(*c.as_ref().unwrap().get_key()));
+
+        let mut j = self.n;
+        while j > pos {
+            self.child[j + 1] = self.child[j].take();
+            j -= 1;
+        }
+
+        self.child[pos + 1] = Some(z);
+
+        let mut j: isize = self.n as isize - 1;
+        while j >= pos as isize {
+            self.keys[(j + 1) as usize] = self.keys[j as usize].take();
+            j -= 1;
+        }
+
+        self.keys[pos] = c;
+        self.n += 1;
+    }
+
+    // Makes room in the full child i before descending into it. Splits it, unless the deferred strategy
+    // finds a sibling with room to spare, which then receives a key through the parent instead.
+    pub fn make_room(&mut self, i: usize, ctx: &mut Context<K, P>) {
+        if ctx.split_strategy == SplitStrategy::BStarDeferred {
+            let spare = |c: &Option<Node<K, P>>| c.as_ref().unwrap().n < 2 * self.t - 2;
+            if i < self.n && spare(&self.child[i + 1]) {
+                self.borrow_from_prev(i + 1);
+                return;
+            }
+            if i > 0 && spare(&self.child[i - 1]) {
+                self.borrow_from_next(i - 1);
+                return;
+            }
+        }
+
+        self.split_nodes(i, i, ctx);
+    }
+
+    pub fn walk_range<R, F>(&self, range: &R, f: &mut F)
+    where
+        R: RangeBounds<K>,
+        F: FnMut(&Entry<K, P>),
+    {
+        let mut i = 0;
+        while i <= self.n {
+            // Child i only holds keys between keys[i - 1] and keys[i]
+            if !self.leaf {
+                let past_end =
+                    i > 0 && after_end(range, self.keys[i - 1].as_ref().unwrap().get_key());
+                let before_start =
+                    i < self.n && before_start(range, self.keys[i].as_ref().unwrap().get_key());
+                if past_end {
+                    return;
+                }
+                if !before_start {
+                    self.child[i].as_ref().unwrap().walk_range(range, f);
+                }
+            }
+
+            if i < self.n {
+                let e = self.keys[i].as_ref().unwrap();
+                if after_end(range, e.get_key()) {
+                    return;
+                }
+                if range.contains(e.get_key()) {
+                    f(e);
+                }
+            }
+            i += 1;
+        }
+    }
+
+    /// Iterates over the keys stored in this node, not its subtree
+    pub fn iter_keys(&self) -> impl Iterator<Item = &K> {
+        self.keys[..self.n].iter().map(|e| &e.as_ref().unwrap().key)
+    }
+
+    // Checks the keys are in ascending order, and every key compares to key consistently with that order
+    pub fn is_sorted_around(&self, key: &K) -> bool {
+        let mut passed = false;
+        let mut prev = None;
+        for k in self.iter_keys() {
+            if prev.is_some_and(|p| p > k) {
+                return false;
+            }
+            prev = Some(k);
+
+            match k.cmp(key) {
+                Ordering::Less if passed => return false,
+                Ordering::Greater => passed = true,
+                _ => {}
+            }
+        }
+        true
+    }
+
+    // Returns the index of the first key that is greater than or equal to key
+    pub fn find_key(&self, key: &K) -> usize {
+        self.iter_keys().take_while(|k| *k < key).count()
+    }
+
+    pub fn remove(&mut self, key: &K, ctx: &mut Context<K, P>) -> Option<Entry<K, P>> {
+        let idx = self.find_key(key);
+
+        let e = if idx < self.n && self.keys[idx].as_ref().unwrap().get_key() == key {
+            // The key is present in this node
+            if self.leaf {
+                self.remove_from_leaf(idx)
+            } else {
+                self.remove_from_non_leaf(idx, ctx)
+            }
+        } else if self.leaf {
+            // The key isn't present in the tree
+            None
+        } else {
+            // Make sure the child we descend into has at least [t] keys,
+            // so a removal from it can never leave it underfull
+            let last = idx == self.n;
+            if self.child[idx].as_ref().unwrap().n < self.t {
+                self.fill(idx, ctx);
+            }
+
+            // If the last child was merged into its left sibling, descend into the sibling
+            if last && idx > self.n {
+                self.child[idx - 1].as_mut().unwrap().remove(key, ctx)
+            } else {
+                self.child[idx].as_mut().unwrap().remove(key, ctx)
+            }
+        };
+
+        if e.is_some() {
+            self.size -= 1;
+        }
+        e
+    }
+
+    // Removes key like `remove`, but without filling up the children on the way down, so nodes may be left
+    // with fewer than t - 1 keys. Only a child left without any keys is merged into a sibling or refilled from it.
+    pub fn remove_lazy(&mut self, key: &K, ctx: &mut Context<K, P>) -> Option<Entry<K, P>> {
+        let idx = self.find_key(key);
+        let found = idx < self.n && self.keys[idx].as_ref().unwrap().get_key() == key;
+
+        let e = if found && self.leaf {
+            self.remove_from_leaf(idx)
+        } else if found {
+            // Replace the key with its predecessor, the last key of the left child's right-most leaf
+            let pred = self.child[idx].as_mut().unwrap().pop_last_lazy(ctx);
+            let e = self.keys[idx].replace(pred);
+            self.fix_empty(idx, ctx);
+            e
+        } else if self.leaf {
+            None
+        } else {
+            let e = self.child[idx].as_mut().unwrap().remove_lazy(key, ctx);
+            self.fix_empty(idx, ctx);
+            e
+        };
+
+        if e.is_some() {
+            self.size -= 1;
+        }
+        e
+    }
+
+    // Removes and returns the last entry of this subtree, as lazily as `remove_lazy`
+    fn pop_last_lazy(&mut self, ctx: &mut Context<K, P>) -> Entry<K, P> {
+        self.size -= 1;
+        if self.leaf {
+            self.n -= 1;
+            return self.keys[self.n].take().unwrap();
+        }
+
+        let last = self.n;
+        let e = self.child[last].as_mut().unwrap().pop_last_lazy(ctx);
+        self.fix_empty(last, ctx);
+        e
+    }
+
+    // Merges the child at idx into a sibling, or borrows a key from a full one, if it was left without keys
+    fn fix_empty(&mut self, idx: usize, ctx: &mut Context<K, P>) {
+        if self.child[idx].as_ref().unwrap().n > 0 {
+            return;
+        }
+
+        let sibling = if idx < self.n { idx + 1 } else { idx - 1 };
+        if self.child[sibling].as_ref().unwrap().n < 2 * self.t - 1 {
+            self.merge(idx.min(sibling), ctx);
+        } else if sibling > idx {
+            self.borrow_from_next(idx);
+        } else {
+            self.borrow_from_prev(idx);
+        }
+    }
+
+    fn remove_from_leaf(&mut self, idx: usize) -> Option<Entry<K, P>> {
+        let e = self.keys[idx].take();
+
+        // Shift every key after idx one step back
+        let mut i = idx + 1;
+        while i < self.n {
+            self.keys[i - 1] = self.keys[i].take();
+            i += 1;
+        }
+
+        self.n -= 1;
+        e
+    }
+
+    fn remove_from_non_leaf(&mut self, idx: usize, ctx: &mut Context<K, P>) -> Option<Entry<K, P>> {
+        let key = *self.keys[idx].as_ref().unwrap().get_key();
+
+        if self.child[idx].as_ref().unwrap().n >= self.t {
+            // Replace the key with its predecessor, taken from the left child
+            let pred = *self.get_pred(idx).get_key();
+            let pred = self.child[idx].as_mut().unwrap().remove(&pred, ctx);
+            std::mem::replace(&mut self.keys[idx], pred)
+        } else if self.child[idx + 1].as_ref().unwrap().n >= self.t {
+            // Replace the key with its successor, taken from the right child
+            let succ = *self.get_succ(idx).get_key();
+            let succ = self.child[idx + 1].as_mut().unwrap().remove(&succ, ctx);
+            std::mem::replace(&mut self.keys[idx], succ)
+        } else {
+            // Both children have [t - 1] keys, merge them around the key and remove it from the result
+            self.merge(idx, ctx);
+            self.child[idx].as_mut().unwrap().remove(&key, ctx)
+        }
+    }
+
+    fn get_pred(&self, idx: usize) -> &Entry<K, P> {
+        // Keep moving to the right-most node until we reach a leaf
+        let mut cur = self.child[idx].as_ref().unwrap();
+        while !cur.leaf {
+            cur = cur.child[cur.n].as_ref().unwrap();
+        }
+
+        cur.keys[cur.n - 1].as_ref().unwrap()
+    }
+
+    fn get_succ(&self, idx: usize) -> &Entry<K, P> {
+        // Keep moving to the left-most node until we reach a leaf
+        let mut cur = self.child[idx + 1].as_ref().unwrap();
+        while !cur.leaf {
+            cur = cur.child[0].as_ref().unwrap();
+        }
+
+        cur.keys[0].as_ref().unwrap()
+    }
+
+    // Fills up the child at idx, which has less than [t - 1] keys
+    fn fill(&mut self, idx: usize, ctx: &mut Context<K, P>) {
+        if idx != 0 && self.child[idx - 1].as_ref().unwrap().n >= self.t {
+            self.borrow_from_prev(idx);
+        } else if idx != self.n && self.child[idx + 1].as_ref().unwrap().n >= self.t {
+            self.borrow_from_next(idx);
+        } else if idx != self.n {
+            self.merge(idx, ctx);
+        } else {
+            self.merge(idx - 1, ctx);
+        }
+    }
+
+    fn borrow_from_prev(&mut self, idx: usize) {
+        let (left, right) = self.child.split_at_mut(idx);
+        let sibling = left[idx - 1].as_mut().unwrap();
+        let child = right[0].as_mut().unwrap();
+
+        // Make room for the key coming down from the parent
+        let mut i = child.n;
+        while i > 0 {
+            child.keys[i] = child.keys[i - 1].take();
+            i -= 1;
+        }
+
+        if !child.leaf {
+            let mut i = child.n + 1;
+            while i > 0 {
+                child.child[i] = child.child[i - 1].take();
+                i -= 1;
+            }
+        }
+
+        // The parent key moves down, the siblings last key moves up to replace it
+        child.keys[0] = self.keys[idx - 1].take();
+        let mut moved = 1;
+        if !child.leaf {
+            child.child[0] = sibling.child[sibling.n].take();
+            moved += child.child[0].as_ref().unwrap().size;
+        }
+        self.keys[idx - 1] = sibling.keys[sibling.n - 1].take();
+
+        child.n += 1;
+        child.size += moved;
+        sibling.n -= 1;
+        sibling.size -= moved;
+    }
+
+    fn borrow_from_next(&mut self, idx: usize) {
+        let (left, right) = self.child.split_at_mut(idx + 1);
+        let child = left[idx].as_mut().unwrap();
+        let sibling = right[0].as_mut().unwrap();
+
+        // The parent key moves down, the siblings first key moves up to replace it
+        child.keys[child.n] = self.keys[idx].take();
+        let mut moved = 1;
+        if !child.leaf {
+            child.child[child.n + 1] = sibling.child[0].take();
+            moved += child.child[child.n + 1].as_ref().unwrap().size;
+        }
+        self.keys[idx] = sibling.keys[0].take();
+
+        // Close the gap left in the sibling
+        let mut i = 1;
+        while i < sibling.n {
+            sibling.keys[i - 1] = sibling.keys[i].take();
+            i += 1;
+        }
+
+        if !sibling.leaf {
+            let mut i = 1;
+            while i <= sibling.n {
+                sibling.child[i - 1] = sibling.child[i].take();
+                i += 1;
+            }
+        }
+
+        child.n += 1;
+        child.size += moved;
+        sibling.n -= 1;
+        sibling.size -= moved;
+    }
+
+    // Merges child[idx + 1] into child[idx], pulling down keys[idx] as the middle key.
+    // The two children must hold at most 2t - 2 keys together.
+    fn merge(&mut self, idx: usize, ctx: &mut Context<K, P>) {
+        let mut sibling = self.child[idx + 1].take().unwrap();
+        let child = self.child[idx].as_mut().unwrap();
+        let m = child.n;
+
+        child.keys[m] = self.keys[idx].take();
+        let separator = *child.keys[m].as_ref().unwrap().get_key();
+
+        let mut i = 0;
+        while i < sibling.n {
+            child.keys[i + m + 1] = sibling.keys[i].take();
+            i += 1;
+        }
+
+        if !child.leaf {
+            let mut i = 0;
+            while i <= sibling.n {
+                child.child[i + m + 1] = sibling.child[i].take();
+                i += 1;
+            }
+        }
+
+        child.n += sibling.n + 1;
+        child.size += sibling.size + 1;
+        ctx.pool.give(sibling);
+        ctx.record(Event::Merge(separator));
+
+        // Close the gap left in this node
+        let mut i = idx + 1;
+        while i < self.n {
+            self.keys[i - 1] = self.keys[i].take();
+            i += 1;
+        }
+
+        let mut i = idx + 2;
+        while i <= self.n {
+            self.child[i - 1] = self.child[i].take();
+            i += 1;
+        }
+
+        self.n -= 1;
+    }
+
+    // Brings every node below this one up to at least t - 1 keys, fixing the children bottom-up.
+    // An underfull child is merged with a sibling if they fit in one node, otherwise it borrows from it.
+    // Returns whether anything was changed.
+    pub fn rebalance(&mut self, ctx: &mut Context<K, P>) -> bool {
+        if self.leaf {
+            return false;
+        }
+
+        let mut changed = false;
+        for i in 0..=self.n {
+            changed |= self.child[i].as_mut().unwrap().rebalance(ctx);
+        }
+
+        let t = self.t;
+        let mut i = 0;
+        while i <= self.n && self.n > 0 {
+            let n = self.child[i].as_ref().unwrap().n;
+            if n >= t - 1 {
+                i += 1;
+                continue;
+            }
+
+            // Prefer the right sibling, the last child only has one on its left
+            let right = i < self.n;
+            let sibling = if right { i + 1 } else { i - 1 };
+            changed = true;
+            if n + self.child[sibling].as_ref().unwrap().n < 2 * t - 1 {
+                // The merged child may still be underfull, so look at it again
+                i = i.min(sibling);
+                self.merge(i, ctx);
+                continue;
+            }
+
+            while self.child[i].as_ref().unwrap().n < t - 1 {
+                if right {
+                    self.borrow_from_next(i);
+                } else {
+                    self.borrow_from_prev(i);
+                }
+            }
+            i += 1;
+        }
+        changed
+    }
+
+    // Bytes allocated on the heap for the key and child slots of this subtree
+    pub fn heap_size(&self) -> usize {
+        let mut bytes = self.child.len() * std::mem::size_of::<Option<Node<K, P>>>();
+        #[cfg(not(feature = "smallvec"))]
+        let inline = false;
+        #[cfg(feature = "smallvec")]
+        let inline = !self.keys.spilled();
+        if !inline {
+            bytes += self.keys.len() * std::mem::size_of::<Option<Entry<K, P>>>();
+        }
+        if !self.leaf {
+            for i in 0..=self.n {
+                bytes += self.child[i].as_ref().unwrap().heap_size();
+            }
+        }
+        bytes
+    }
+
+    pub fn offset_keys(&mut self, offset: K)
+    where
+        K: Add<Output = K>,
+    {
+        for i in 0..self.n {
+            let e = self.keys[i].as_mut().unwrap();
+            e.key = e.key + offset;
+        }
+
+        if !self.leaf {
+            for i in 0..=self.n {
+                self.child[i].as_mut().unwrap().offset_keys(offset);
+            }
+        }
+    }
+
+    // Applies update to the value of every entry in this subtree whose key is selected, returns how many were updated
+    pub fn update_matching<S, F>(&mut self, select: &S, update: &mut F) -> usize
+    where
+        S: Fn(&K) -> bool,
+        F: FnMut(&mut P),
+    {
+        let mut count = 0;
+        for i in 0..self.n {
+            let e = self.keys[i].as_mut().unwrap();
+            if select(&e.key) {
+                update(&mut e.value);
+                count += 1;
+            }
+        }
+
+        if !self.leaf {
+            for i in 0..=self.n {
+                count += self.child[i]
+                    .as_mut()
+                    .unwrap()
+                    .update_matching(select, update);
+            }
+        }
+        count
+    }
+}
+
+pub struct BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    pub(crate) root: Option<Node<K, P>>,
+    t: usize,
+    checked: bool,
+    ctx: Context<K, P>,
+    // Smallest and largest key, kept up to date so min and max don't have to descend
+    min_key: Option<K>,
+    max_key: Option<K>,
+    // Most entries try_insert allows, see set_max_len
+    max_len: Option<usize>,
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    pub fn new(t: usize) -> BTree<K, P> {
+        if t < 2 {
+            panic!("Degree may not be smaller than 2");
+        }
+
+        BTree {
+            root: None,
+            t,
+            checked: false,
+            ctx: Context::new(),
+            min_key: None,
+            max_key: None,
+            max_len: None,
+        }
+    }
+
+    /// Creates a tree that keeps the nodes emptied by removals in a free-list,
+    /// and reuses them for splits instead of allocating new nodes.
+    /// This takes pressure off the allocator in workloads that constantly insert and remove.
+    pub fn with_node_recycler(t: usize) -> BTree<K, P> {
+        let mut tree = BTree::new(t);
+        tree.ctx.pool = NodePool::new(true);
+        tree
+    }
+
+    /// Creates a tree that counts its inserts, removes, searches, splits and merges, see `op_stats`.
+    /// Bulk operations that rebuild the tree aren't counted.
+    pub fn with_stats(t: usize) -> BTree<K, P> {
+        let mut tree = BTree::new(t);
+        tree.ctx.stats = Some(context::AtomicStats::default());
+        tree
+    }
+
+    /// Creates a tree where inserting a key that's already there doesn't add another entry,
+    /// but stores `f(old, new)` as its value instead, e.g. to sum or keep the maximum.
+    /// Applies to `insert` and the methods built on it, like `try_insert` and `insert_ref`.
+    /// f has to be `Send + Sync`, so that the tree can still be shared between threads.
+    pub fn with_merge_fn<F>(t: usize, f: F) -> BTree<K, P>
+    where
+        F: Fn(P, P) -> P + Send + Sync + 'static,
+    {
+        let mut tree = BTree::new(t);
+        tree.ctx.merge_fn = Some(Box::new(f));
+        tree
+    }
+
+    /// Returns the operation counters, which stay zero unless the tree was created by `with_stats`
+    pub fn op_stats(&self) -> OpStats {
+        match &self.ctx.stats {
+            Some(stats) => stats.load(),
+            None => OpStats::default(),
+        }
+    }
+
+    /// Chooses how inserts make room in full nodes from now on, see `SplitStrategy`
+    pub fn set_split_strategy(&mut self, strategy: SplitStrategy) {
+        self.ctx.split_strategy = strategy;
+    }
+
+    /// Creates a tree that verifies, in debug builds, the nodes along the path of every insert stay sorted.
+    /// An inconsistent `Ord` implementation then panics at the insert that exposes it,
+    /// instead of silently corrupting the tree.
+    pub fn checked(t: usize) -> BTree<K, P> {
+        let mut tree = BTree::new(t);
+        tree.checked = true;
+        tree
+    }
+
+    /// Takes the tree apart into its root node and degree, e.g. for external visualizers or serializers.
+    /// The rest of the tree's state, like the node recycler and the stats, is dropped.
+    pub fn into_parts(self) -> (Option<Node<K, P>>, usize) {
+        (self.root, self.t)
+    }
+
+    /// Puts a tree back together from a root node and degree, the inverse of `into_parts`.
+    /// The root is trusted as is: it has to be a valid B-tree of degree t, with every node's `t`, `n`,
+    /// `size` and `leaf` matching its slots, keys in order and all leaves at the same depth.
+    /// Otherwise lookups and updates give wrong results or panic.
+    pub fn from_parts(root: Option<Node<K, P>>, t: usize) -> BTree<K, P> {
+        let mut tree = BTree::new(t);
+        tree.root = root;
+        tree.refresh_extremes();
+        tree
+    }
+
+    /// Calls f with the separator key every time a split promotes one to its parent,
+    /// e.g. to monitor where the tree splits while tuning its degree. Replaces any earlier observer.
+    /// f has to be `Send + Sync`, so that the tree can still be shared between threads.
+    pub fn observe_splits<F>(&mut self, f: F)
+    where
+        F: FnMut(&K) + Send + Sync + 'static,
+    {
+        self.ctx.split_observer = Some(Box::new(f));
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.root {
+            Some(r) => r.size,
+            None => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Returns the smallest key in O(1), it's cached and kept up to date by every change to the tree
+    pub fn min(&self) -> Option<&K> {
+        self.min_key.as_ref()
+    }
+
+    /// Returns the largest key in O(1), see `min`
+    pub fn max(&self) -> Option<&K> {
+        self.max_key.as_ref()
+    }
+
+    // Recomputes the cached extremes by descending to the left-most and right-most entries
+    pub(crate) fn refresh_extremes(&mut self) {
+        self.min_key = None;
+        self.max_key = None;
+
+        let root = match &self.root {
+            Some(r) => r,
+            None => return,
+        };
+
+        let mut node = root;
+        while !node.leaf {
+            node = node.child[0].as_ref().unwrap();
+        }
+        self.min_key = Some(node.keys[0].as_ref().unwrap().key);
+
+        let mut node = root;
+        while !node.leaf {
+            node = node.child[node.n].as_ref().unwrap();
+        }
+        self.max_key = Some(node.keys[node.n - 1].as_ref().unwrap().key);
+    }
+
+    pub fn traverse(&self) -> Option<Vec<&Entry<K, P>>> {
+        let mut t = Vec::new();
+
+        match &self.root {
+            Some(r) => {
+                r.traverse(&mut t);
+                Some(t)
+            }
+            None => None,
+        }
+    }
+
+    pub fn search(&self, key: &K) -> Option<Entry<K, P>> {
+        self.ctx.count(|s| &s.searches);
+        let e = match &self.root {
+            Some(r) => r.search(key, false),
+            None => None,
+        };
+
+        #[cfg(feature = "access_tracking")]
+        if let Some(e) = &e {
+            self.ctx.hit(e.key);
+        }
+        e
+    }
+
+    pub fn search_linear(&self, key: &K) -> Option<Entry<K, P>> {
+        self.ctx.count(|s| &s.searches);
+        let e = match &self.root {
+            Some(r) => r.search(key, true),
+            None => None,
+        };
+
+        #[cfg(feature = "access_tracking")]
+        if let Some(e) = &e {
+            self.ctx.hit(e.key);
+        }
+        e
+    }
+
+    /// Like `search`, but gives up once max_comparisons key comparisons haven't been enough to find
+    /// the key or rule it out, which bounds the latency of a lookup.
+    /// Every node is binary searched, so a lookup takes about log2(len) comparisons.
+    pub fn search_budgeted(
+        &self,
+        key: &K,
+        max_comparisons: usize,
+    ) -> Result<Option<Entry<K, P>>, BudgetExceeded> {
+        let mut budget = max_comparisons;
+        let mut node = match &self.root {
+            Some(r) => r,
+            None => return Ok(None),
+        };
+
+        loop {
+            let mut low = 0;
+            let mut high = node.n;
+            while low < high {
+                if budget == 0 {
+                    return Err(BudgetExceeded);
+                }
+                budget -= 1;
+
+                let mid = low + ((high - low) / 2);
+                match node.keys[mid].as_ref().unwrap().get_key().cmp(key) {
+                    Ordering::Equal => {
+                        #[cfg(feature = "access_tracking")]
+                        self.ctx.hit(*key);
+                        return Ok(node.keys[mid]);
+                    }
+                    Ordering::Greater => high = mid,
+                    Ordering::Less => low = mid + 1,
+                }
+            }
+
+            if node.leaf {
+                return Ok(None);
+            }
+            node = node.child[low].as_ref().unwrap();
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&P>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.ctx.count(|s| &s.searches);
+        let e = self.root.as_ref()?.find(key)?;
+        #[cfg(feature = "access_tracking")]
+        self.ctx.hit(e.key);
+        Some(&e.value)
+    }
+
+    /// Like `get`, but wraps the value in a borrowed `Cow`, so callers can take ownership with `into_owned` only when they need it
+    pub fn get_cow(&self, key: &K) -> Option<Cow<'_, P>> {
+        self.get(key).map(Cow::Borrowed)
+    }
+
+    /// Returns the floor and the ceiling of key, the entries with the closest keys at or below and at or above it,
+    /// e.g. to interpolate between them. If key is present both are its entry.
+    pub fn bracket(&self, key: &K) -> Bracket<'_, K, P> {
+        self.ctx.count(|s| &s.searches);
+        match &self.root {
+            Some(r) => r.bracket(key),
+            None => (None, None),
+        }
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut P>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.ctx.count(|s| &s.searches);
+        let e = self.root.as_mut()?.find_mut(key)?;
+        #[cfg(feature = "access_tracking")]
+        self.ctx.hit(e.key);
+        Some(&mut e.value)
+    }
+
+    /// Replaces the value of key with new, but only if it currently equals expected, in one descent.
+    /// Returns the current value if it doesn't, or `None` if the key isn't in the tree.
+    pub fn compare_and_swap(&mut self, key: &K, expected: &P, new: P) -> Result<(), Option<P>>
+    where
+        P: PartialEq,
+    {
+        self.ctx.count(|s| &s.searches);
+        let e = match &mut self.root {
+            Some(r) => r.find_mut(key).ok_or(None)?,
+            None => return Err(None),
+        };
+        #[cfg(feature = "access_tracking")]
+        self.ctx.hit(e.key);
+
+        if e.value != *expected {
+            return Err(Some(e.value));
+        }
+        e.value = new;
+        Ok(())
+    }
+
+    /// Returns whether every one of keys is in the tree.
+    /// The keys are sorted and matched in a single in-order pass, instead of a descent per key.
+    pub fn contains_all(&self, keys: &[K]) -> bool {
+        self.sorted_membership(keys).all(|found| found)
+    }
+
+    /// Returns whether any of keys is in the tree, see `contains_all`
+    pub fn contains_any(&self, keys: &[K]) -> bool {
+        self.sorted_membership(keys).any(|found| found)
+    }
+
+    // Yields whether each of keys, in sorted order, is in the tree, moving a single cursor forward
+    fn sorted_membership(&self, keys: &[K]) -> impl Iterator<Item = bool> + '_ {
+        let mut sorted = keys.to_vec();
+        sorted.sort();
+
+        let mut entries = self.iter().peekable();
+        sorted.into_iter().map(move |k| {
+            while entries.next_if(|e| e.key < k).is_some() {}
+            entries.peek().is_some_and(|e| e.key == k)
+        })
+    }
+
+    /// Returns the first entry, in key order, for which pred returns true, stopping the walk there
+    pub fn find<F>(&self, pred: F) -> Option<&Entry<K, P>>
+    where
+        F: Fn(&K, &P) -> bool,
+    {
+        self.iter().find(|e| pred(&e.key, &e.value))
+    }
+
+    /// Collects every entry, in key order, for which f returns true. Always scans the whole tree.
+    pub fn find_all<F>(&self, mut f: F) -> Vec<(&K, &P)>
+    where
+        F: FnMut(&K, &P) -> bool,
+    {
+        self.iter()
+            .filter(|e| f(&e.key, &e.value))
+            .map(|e| (&e.key, &e.value))
+            .collect()
+    }
+
+    pub fn insert(&mut self, key: K, pointer: P) {
+        self.insert_ranked(key, pointer);
+    }
+
+    /// Updates the value if key is already in the tree, and only inserts it otherwise.
+    /// Looking the key up first costs a descent, but an update then doesn't split full nodes on its way down,
+    /// which pays off when most inserts are updates. Returns whether a new entry was inserted.
+    /// With a merge function, see `with_merge_fn`, an update stores `f(old, pointer)` like `insert` would.
+    pub fn insert_before_split_check(&mut self, key: K, pointer: P) -> bool {
+        self.ctx.count(|s| &s.searches);
+        if let Some(e) = self.root.as_mut().and_then(|r| r.find_mut(&key)) {
+            #[cfg(feature = "access_tracking")]
+            self.ctx.hit(key);
+            e.value = match &self.ctx.merge_fn {
+                Some(f) => f(e.value, pointer),
+                None => pointer,
+            };
+            return false;
+        }
+
+        self.insert(key, pointer);
+        true
+    }
+
+    /// Limits how many entries `try_insert` and `insert_transaction` let the tree grow to, `None` lifts the limit.
+    /// `insert` can't fail, so it isn't limited.
+    pub fn set_max_len(&mut self, limit: Option<usize>) {
+        self.max_len = limit;
+    }
+
+    /// Inserts like `insert`, unless the tree already holds as many entries as `set_max_len` allows.
+    /// A tree with a merge function still takes keys it already holds at the limit, as merging doesn't add an entry.
+    pub fn try_insert(&mut self, key: K, pointer: P) -> Result<(), BTreeError> {
+        if let Some(limit) = self.max_len {
+            let merges = self.ctx.merge_fn.is_some()
+                && self.root.as_ref().is_some_and(|r| r.find(&key).is_some());
+            if self.len() >= limit && !merges {
+                return Err(BTreeError::CapacityExceeded { limit });
+            }
+        }
+
+        self.insert(key, pointer);
+        Ok(())
+    }
+
+    /// Inserts like `insert` and returns a mutable reference to the stored value.
+    /// Splits on the way down can move the entry, the reference points at where it ended up.
+    pub fn insert_ref(&mut self, key: K, pointer: P) -> &mut P {
+        // The rank tells the new entry apart from any other entries with the same key
+        let rank = self.insert_ranked(key, pointer);
+        let e = self.root.as_mut().unwrap().select_mut(rank).unwrap();
+        &mut e.value
+    }
+
+    /// Inserts like `insert` and returns the in-order position the key ended up at, found from the subtree sizes on the way down.
+    /// This is the rank at insert time, later inserts and removals of smaller keys shift it.
+    pub fn insert_ranked(&mut self, key: K, pointer: P) -> usize {
+        self.ctx.count(|s| &s.inserts);
+
+        // With a merge function an existing entry takes the merged value instead of getting a duplicate
+        if let Some(f) = &self.ctx.merge_fn {
+            if let Some(e) = self.root.as_mut().and_then(|r| r.find_mut(&key)) {
+                e.value = f(e.value, pointer);
+                return self.rank(&key);
+            }
+        }
+
+        // Initialize new root if it doesn't already exist
+        // Insert directly into it if it's new
+        let rank = if self.root.is_none() {
+            let mut root = self.ctx.pool.take(self.t, true);
+            root.keys[0] = Some(Entry::new(key, pointer));
+            root.n = 1;
+            root.size = 1;
+            self.root = Some(root);
+            0
+        } else {
+            // Check if root is full
+            if self.root.as_ref().unwrap().n == 2 * self.t - 1 {
+                self.split_root();
+            }
+
+            // The root has room now, insert into it
+            self.root
+                .as_mut()
+                .unwrap()
+                .insert_non_full(key, pointer, &mut self.ctx)
+        };
+
+        widen_extremes(&mut self.min_key, &mut self.max_key, key);
+
+        if self.checked {
+            self.debug_check_path(&key);
+        }
+        rank
+    }
+
+    // Grows the tree by one level, moving the full root under a new root and splitting it
+    pub(crate) fn split_root(&mut self) {
+        // Initialize a new root, prepare for a split
+        let mut s = self.ctx.pool.take(self.t, false);
+
+        // Steal the root and set it as a child of the new root,
+        s.child[0] = self.root.take();
+        s.size = s.child[0].as_ref().unwrap().size;
+
+        // Split the old root, by the child of index 0
+        s.split_nodes(0, 0, &mut self.ctx);
+
+        self.root = Some(s);
+    }
+
+    // Asserts every node on the path to key is still sorted, and agrees with key about where it belongs
+    fn debug_check_path(&self, key: &K) {
+        let mut node = self.root.as_ref();
+        while let Some(n) = node {
+            debug_assert!(
+                n.is_sorted_around(key),
+                "Node keys are out of order after an insert, the key ordering is inconsistent"
+            );
+
+            let i = n.find_key(key);
+            if n.leaf || (i < n.n && n.keys[i].as_ref().unwrap().get_key() == key) {
+                break;
+            }
+            node = n.child[i].as_ref();
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<Entry<K, P>> {
+        self.ctx.count(|s| &s.removes);
+        let root = self.root.as_mut()?;
+        let e = root.remove(key, &mut self.ctx);
+        self.after_remove(e)
+    }
+
+    /// Removes an entry with key like `remove`, but leaves the nodes on the way down as they are
+    /// instead of filling them up first, so a removal touches only the nodes along the path to the key.
+    /// Nodes may be left with fewer than t - 1 keys, though never with none, until `rebalance` fixes them,
+    /// e.g. after a batch of removals. Lookups and iteration work as usual in the meantime.
+    pub fn remove_lazy(&mut self, key: &K) -> Option<Entry<K, P>> {
+        self.ctx.count(|s| &s.removes);
+        let root = self.root.as_mut()?;
+        let e = root.remove_lazy(key, &mut self.ctx);
+        self.after_remove(e)
+    }
+
+    // Shrinks the tree if the root ran out of keys, and updates the cached extremes and hits after removing e
+    fn after_remove(&mut self, e: Option<Entry<K, P>>) -> Option<Entry<K, P>> {
+        // Shrink the tree if the root ran out of keys
+        let root = self.root.as_mut().unwrap();
+        if root.n == 0 {
+            let next = root.child[0].take();
+            if let Some(old) = std::mem::replace(&mut self.root, next) {
+                self.ctx.pool.give(old);
+            }
+        }
+
+        // Losing an extreme takes a fresh descent to find the new one
+        if let Some(e) = &e {
+            if Some(e.key) == self.min_key || Some(e.key) == self.max_key {
+                self.refresh_extremes();
+            }
+        }
+
+        // Forget the hits of a key once its last entry is gone
+        #[cfg(feature = "access_tracking")]
+        if let Some(e) = &e {
+            if self.root.as_ref().and_then(|r| r.find(&e.key)).is_none() {
+                self.ctx.hits.get_mut().unwrap().remove(&e.key);
+            }
+        }
+
+        e
+    }
+
+    // Forgets the hits of every key that is no longer in the tree, after entries were removed in bulk
+    pub(crate) fn forget_removed_hits(&mut self) {
+        #[cfg(feature = "access_tracking")]
+        {
+            let root = self.root.as_ref();
+            let hits = self.ctx.hits.get_mut().unwrap();
+            hits.retain(|k, _| root.is_some_and(|r| r.find(k).is_some()));
+        }
+    }
+
+    /// Merges or refills every node left with fewer than t - 1 keys, e.g. after a batch of `remove_lazy`,
+    /// bottom-up like the standard removal rules.
+    /// Unlike `compact` the tree is fixed in place, so nodes that don't need to change are kept as they are.
+    pub fn rebalance(&mut self) {
+        if let Some(r) = &mut self.root {
+            // Merging children can leave their parents underfull, so repeat until nothing changes
+            while r.rebalance(&mut self.ctx) {}
+        }
+
+        // The root may have run out of keys
+        while self.root.as_ref().is_some_and(|r| r.n == 0) {
+            let next = self.root.as_mut().unwrap().child[0].take();
+            if let Some(old) = std::mem::replace(&mut self.root, next) {
+                self.ctx.pool.give(old);
+            }
+        }
+        self.refresh_extremes();
+    }
+
+    pub fn retain_range<R, F>(&mut self, range: R, mut f: F)
+    where
+        R: RangeBounds<K>,
+        F: FnMut(&K, &P) -> bool,
+    {
+        // Collect the keys to drop first, removals restructure the nodes we'd be walking
+        let mut dropped = Vec::new();
+        if let Some(r) = &self.root {
+            r.walk_range(&range, &mut |e| {
+                if !f(&e.key, &e.value) {
+                    dropped.push(e.key);
+                }
+            });
+        }
+
+        for key in dropped.iter() {
+            self.remove(key);
+        }
+    }
+
+    /// Adds offset to every key. Adding a constant keeps the keys in the same order,
+    /// so the keys are updated in place without restructuring the tree.
+    /// The addition must not overflow or wrap around, as that would change the order.
+    pub fn rotate_by(&mut self, offset: K)
+    where
+        K: Add<Output = K>,
+    {
+        if let Some(r) = &mut self.root {
+            r.offset_keys(offset);
+        }
+        self.min_key = self.min_key.map(|k| k + offset);
+        self.max_key = self.max_key.map(|k| k + offset);
+    }
+
+    /// Applies update to the value of every entry whose key is selected, in a single traversal.
+    /// Returns how many values were updated.
+    pub fn update_matching<S, F>(&mut self, select: S, mut update: F) -> usize
+    where
+        S: Fn(&K) -> bool,
+        F: FnMut(&mut P),
+    {
+        match &mut self.root {
+            Some(r) => r.update_matching(&select, &mut update),
+            None => 0,
+        }
+    }
+
+    /// Returns the steps a search for key takes, one `(separator, index)` pair per node from the root down, for debugging.
+    /// index is the child the search descends into, the separator the first key in the node above key,
+    /// or the last key if the search goes past all of them. The last step is the key itself and its index if it's found,
+    /// otherwise the leaf position it would be inserted at.
+    pub fn path_to(&self, key: &K) -> Vec<(&K, usize)> {
+        let mut path = Vec::new();
+        let mut node = match &self.root {
+            Some(r) => r,
+            None => return path,
+        };
+
+        loop {
+            let i = node.find_key(key);
+            if i < node.n && node.keys[i].as_ref().unwrap().key == *key {
+                path.push((&node.keys[i].as_ref().unwrap().key, i));
+                return path;
+            }
+
+            let separator = node.keys[i.min(node.n - 1)].as_ref().unwrap();
+            path.push((&separator.key, i));
+            if node.leaf {
+                return path;
+            }
+            node = node.child[i].as_ref().unwrap();
+        }
+    }
+
+    /// Dumps the raw node structure, including the empty key and child slots.
+    /// The `Debug` output only lists the entries.
+    pub fn debug_structure(&self) -> String
+    where
+        K: fmt::Debug,
+        P: fmt::Debug,
+    {
+        format!("{:#?}", self.root)
+    }
+
+    /// Returns the entries of every leaf, left to right, to inspect how entries are spread over the leaves.
+    /// Entries held by internal nodes aren't included.
+    pub fn to_nested_vec(&self) -> Vec<Vec<(K, P)>> {
+        // Every leaf is on the bottom level
+        let leaves = match self.level_iter().last() {
+            Some(l) => l,
+            None => return Vec::new(),
+        };
+
+        leaves
+            .iter()
+            .map(|n| {
+                n.keys[..n.n]
+                    .iter()
+                    .map(|e| e.as_ref().unwrap().into_pair())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Counts the leaves, visiting every node
+    pub fn leaf_count(&self) -> usize {
+        // Every leaf is on the bottom level
+        self.level_iter().last().map_or(0, |l| l.len())
+    }
+
+    /// Counts the nodes that aren't leaves, visiting every node
+    pub fn internal_node_count(&self) -> usize {
+        self.level_iter()
+            .filter(|level| !level[0].leaf)
+            .map(|level| level.len())
+            .sum()
+    }
+
+    /// Returns the number of empty key slots in the allocated nodes, `nodes * (2t - 1) - len`.
+    /// Only approximates how many more inserts fit before a split, as an insert can only use the slots of its own leaf.
+    pub fn capacity(&self) -> usize {
+        let nodes = self.leaf_count() + self.internal_node_count();
+        nodes * (2 * self.t - 1) - self.len()
+    }
+
+    /// Estimates the bytes used by the tree, e.g. to enforce a memory budget in a cache.
+    /// Counts the tree itself and the key and child slots of every node, which hold the nodes
+    /// and entries inline, including nodes kept for reuse by the node recycler.
+    /// Allocator overhead and memory owned by the keys or values aren't included.
+    pub fn approximate_memory_usage(&self) -> usize {
+        let mut bytes = std::mem::size_of::<Self>();
+        if let Some(r) = &self.root {
+            bytes += r.heap_size();
+        }
+        bytes + self.ctx.pool.heap_size()
+    }
+
+    /// Estimates the bytes a tree of degree t holding n entries takes, the way `approximate_memory_usage` counts them,
+    /// without building it. Assumes the worst case of every node holding only `t - 1` keys, `ceil(n / (t - 1))` nodes.
+    pub fn estimate_size_for(n: usize, t: usize) -> usize {
+        if t < 2 {
+            panic!("Degree may not be smaller than 2");
+        }
+
+        // Every node allocates the same slots, whatever it holds
+        let per_node = Node::<K, P>::new(t, true).heap_size();
+        std::mem::size_of::<Self>() + n.div_ceil(t - 1) * per_node
+    }
+}
+
+// Number of entries the Debug output lists before cutting off
+const DEBUG_ENTRIES: usize = 16;
+
+impl<K, P> fmt::Debug for BTree<K, P>
+where
+    K: Eq + Ord + Copy + fmt::Debug,
+    P: Copy + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Entries<'a, K: Eq + Ord + Copy, P: Copy>(&'a BTree<K, P>);
+
+        impl<K, P> fmt::Debug for Entries<'_, K, P>
+        where
+            K: Eq + Ord + Copy + fmt::Debug,
+            P: Copy + fmt::Debug,
+        {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let mut list = f.debug_list();
+                list.entries(self.0.iter().take(DEBUG_ENTRIES).map(|e| (e.key, e.value)));
+                if self.0.len() > DEBUG_ENTRIES {
+                    list.finish_non_exhaustive()
+                } else {
+                    list.finish()
+                }
+            }
+        }
+
+        f.debug_struct("BTree")
+            .field("t", &self.t)
+            .field("len", &self.len())
+            .field("entries", &Entries(self))
+            .finish()
+    }
+}
+
+/// Returns the smallest degree `t` for which a tree of `n` keys is guaranteed to be at most `target_height` levels high.
+/// A tree one level higher than that would need at least `2t^target_height - 1` keys,
+/// as the root holds at least one key and every other node at least `t - 1`.
+pub fn suggest_degree(n: usize, target_height: usize) -> usize {
+    if target_height == 0 {
+        panic!("Target height has to be at least 1");
+    }
+
+    let fits = |t: usize| match t.checked_pow(target_height as u32) {
+        Some(p) => p.saturating_mul(2) - 1 > n,
+        None => true,
+    };
+
+    // Double until the degree fits, then narrow it down
+    let mut low = 2;
+    let mut high = 2;
+    while !fits(high) {
+        low = high + 1;
+        high *= 2;
+    }
+
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if fits(mid) {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+
+    high
+}
@@ -0,0 +1,410 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use memmap2::Mmap;
+
+use crate::{BTree, FixedSized, Node};
+
+// File layout, all integers little endian:
+//
+//   header: magic (8 bytes), t, len, key size, value size, root offset (u64 each, root offset 0 if empty)
+//   nodes:  one record per node, children are written before their parent
+//
+// Every node record has the same size for a given t:
+//
+//   leaf (u64, 0 or 1), n (u64), 2t - 1 (key, value) slots, 2t child offsets (u64)
+const MAGIC: &[u8; 8] = b"BTREEMM1";
+const HEADER_SIZE: usize = 48;
+
+fn record_size(t: usize, key_size: usize, value_size: usize) -> Option<usize> {
+    let slots = t.checked_sub(1)?.checked_mul(2)?.checked_add(1)?;
+    let children = slots.checked_add(1)?.checked_mul(8)?;
+    slots
+        .checked_mul(key_size.checked_add(value_size)?)?
+        .checked_add(children)?
+        .checked_add(16)
+}
+
+fn read_u64(buf: &[u8], at: usize) -> u64 {
+    u64::decode(&buf[at..at + 8])
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl<K, P> Node<K, P>
+where
+    K: Eq + Ord + Copy + FixedSized,
+    P: Copy + FixedSized,
+{
+    // Writes the subtree in post-order, returning the offset this node's record was written at
+    fn write_records<W: Write>(&self, w: &mut W, next_offset: &mut u64) -> io::Result<u64> {
+        let mut children = vec![0u64; 2 * self.t];
+        if !self.leaf {
+            for (c, offset) in self.child[..=self.n].iter().zip(children.iter_mut()) {
+                *offset = c.as_ref().unwrap().write_records(w, next_offset)?;
+            }
+        }
+
+        let slot = K::SIZE + P::SIZE;
+        let mut record = vec![0u8; record_size(self.t, K::SIZE, P::SIZE).unwrap()];
+        (self.leaf as u64).encode(&mut record[0..8]);
+        (self.n as u64).encode(&mut record[8..16]);
+        for i in 0..self.n {
+            let e = self.keys[i].as_ref().unwrap();
+            let at = 16 + i * slot;
+            e.key.encode(&mut record[at..at + K::SIZE]);
+            e.value.encode(&mut record[at + K::SIZE..at + slot]);
+        }
+        let at = 16 + (2 * self.t - 1) * slot;
+        for (i, c) in children.iter().enumerate() {
+            c.encode(&mut record[at + i * 8..at + i * 8 + 8]);
+        }
+
+        w.write_all(&record)?;
+        let offset = *next_offset;
+        *next_offset += record.len() as u64;
+        Ok(offset)
+    }
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy + FixedSized,
+    P: Copy + FixedSized,
+{
+    /// Writes the tree to path in a format that `open` can memory-map and search without deserializing it.
+    /// The file is written next to path and renamed over it once it's synced,
+    /// so a crash leaves either the old or the new file in place, never a partial one.
+    pub fn save<T: AsRef<Path>>(&self, path: T) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut tmp = PathBuf::from(path).into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+
+        let mut w = BufWriter::new(File::create(&tmp)?);
+
+        // The root goes last, so the header is filled in once every node is written
+        w.write_all(&[0; HEADER_SIZE])?;
+        let mut next_offset = HEADER_SIZE as u64;
+        let root = match &self.root {
+            Some(r) => r.write_records(&mut w, &mut next_offset)?,
+            None => 0,
+        };
+
+        let mut header = [0u8; HEADER_SIZE];
+        header[0..8].copy_from_slice(MAGIC);
+        (self.t as u64).encode(&mut header[8..16]);
+        (self.len() as u64).encode(&mut header[16..24]);
+        (K::SIZE as u64).encode(&mut header[24..32]);
+        (P::SIZE as u64).encode(&mut header[32..40]);
+        root.encode(&mut header[40..48]);
+        w.seek(SeekFrom::Start(0))?;
+        w.write_all(&header)?;
+
+        let file = w.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)?;
+
+        // Persist the rename itself, where the platform lets us open directories
+        if let Some(dir) = path.parent() {
+            let dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+            if let Ok(d) = File::open(dir) {
+                let _ = d.sync_all();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Memory-maps a tree written by `save`. Nodes are read straight from the mapping on every lookup,
+    /// nothing is loaded onto the heap up front.
+    ///
+    /// The file must not be modified while it's mapped.
+    pub fn open<T: AsRef<Path>>(path: T) -> io::Result<MappedBTree<K, P>> {
+        let file = File::open(path)?;
+
+        // Safety: the mapping is read-only, and the caller guarantees the file isn't modified while mapped
+        let map = unsafe { Mmap::map(&file)? };
+
+        if map.len() < HEADER_SIZE || &map[0..8] != MAGIC {
+            return Err(invalid("not a saved btree"));
+        }
+
+        let t = read_u64(&map, 8) as usize;
+        let len = read_u64(&map, 16) as usize;
+        if read_u64(&map, 24) as usize != K::SIZE || read_u64(&map, 32) as usize != P::SIZE {
+            return Err(invalid("key or value size doesn't match the file"));
+        }
+        if t < 2 {
+            return Err(invalid("degree may not be smaller than 2"));
+        }
+
+        let record = match record_size(t, K::SIZE, P::SIZE) {
+            Some(r) => r,
+            None => return Err(invalid("degree is too large")),
+        };
+        if (map.len() - HEADER_SIZE) % record != 0 {
+            return Err(invalid("file isn't made up of whole node records"));
+        }
+
+        let root = read_u64(&map, 40) as usize;
+        let mut tree = MappedBTree {
+            map,
+            t,
+            len,
+            root,
+            record,
+            height: 0,
+            marker: PhantomData,
+        };
+        if (len == 0) != (root == 0) {
+            return Err(invalid("root offset doesn't match the length"));
+        }
+        if root == 0 {
+            return Ok(tree);
+        }
+
+        // Walk down the leftmost path to learn the height, which bounds every other path.
+        // A cycle would make it longer than the number of records, so the walk always ends.
+        let records = (tree.map.len() - HEADER_SIZE) / record;
+        let mut offset = root;
+        loop {
+            let (leaf, n) = match tree.node(offset) {
+                Some((_, leaf, n)) => (leaf, n),
+                None => return Err(invalid("corrupt node record")),
+            };
+            tree.height += 1;
+            if leaf {
+                break;
+            }
+            if tree.height >= records {
+                return Err(invalid("node offsets form a cycle"));
+            }
+            offset = tree.child_offset(offset, n, 0);
+        }
+
+        Ok(tree)
+    }
+}
+
+/// A read-only tree served straight from a memory-mapped file written by `BTree::save`
+#[derive(Debug)]
+pub struct MappedBTree<K, P>
+where
+    K: Eq + Ord + Copy + FixedSized,
+    P: Copy + FixedSized,
+{
+    map: Mmap,
+    t: usize,
+    len: usize,
+    root: usize,
+    record: usize,
+    // Levels along the leftmost path, every leaf has to be this deep
+    height: usize,
+    marker: PhantomData<(K, P)>,
+}
+
+impl<K, P> MappedBTree<K, P>
+where
+    K: Eq + Ord + Copy + FixedSized,
+    P: Copy + FixedSized,
+{
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn degree(&self) -> usize {
+        self.t
+    }
+
+    /// Looks key up in the mapped file. A lookup that runs into a corrupt node record returns None.
+    pub fn search(&self, key: &K) -> Option<P> {
+        if self.root == 0 {
+            return None;
+        }
+
+        let slot = K::SIZE + P::SIZE;
+        let mut offset = self.root;
+        let mut depth = 1;
+        loop {
+            let (node, leaf, n) = self.node(offset)?;
+
+            // Leaves only at the bottom, so a path can't loop back on itself
+            if leaf != (depth == self.height) {
+                return None;
+            }
+
+            // Binary search the keys, decoding only the ones we compare against
+            let mut low = 0;
+            let mut high = n;
+            while low < high {
+                let mid = low + (high - low) / 2;
+                let at = 16 + mid * slot;
+                let k = K::decode(&node[at..at + K::SIZE]);
+                if k == *key {
+                    return Some(P::decode(&node[at + K::SIZE..at + slot]));
+                }
+                if k < *key {
+                    low = mid + 1;
+                } else {
+                    high = mid;
+                }
+            }
+
+            if leaf {
+                return None;
+            }
+
+            offset = self.child_offset(offset, n, low);
+            depth += 1;
+        }
+    }
+
+    // The record at offset with its leaf flag and key count, or None if the offset or record is corrupt
+    fn node(&self, offset: usize) -> Option<(&[u8], bool, usize)> {
+        if offset < HEADER_SIZE || (offset - HEADER_SIZE) % self.record != 0 {
+            return None;
+        }
+        let node = self.map.get(offset..offset.checked_add(self.record)?)?;
+
+        let leaf = match read_u64(node, 0) {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+        let n = read_u64(node, 8) as usize;
+        if n == 0 || n >= 2 * self.t {
+            return None;
+        }
+
+        Some((node, leaf, n))
+    }
+
+    // Offset of the i-th child of the node at offset, which has already been checked by `node`
+    fn child_offset(&self, offset: usize, n: usize, i: usize) -> usize {
+        debug_assert!(i <= n);
+        let at = offset + 16 + (2 * self.t - 1) * (K::SIZE + P::SIZE) + i * 8;
+        read_u64(&self.map, at) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::HEADER_SIZE;
+    use crate::tests::allocations;
+    use crate::{BTree, FixedSized};
+
+    // A file in the temp dir that's removed again when the test is done
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> TempFile {
+            let name = format!("btree-{}-{}", std::process::id(), name);
+            TempFile(std::env::temp_dir().join(name))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn saved_tree(file: &TempFile, n: u64) -> Vec<u8> {
+        let tree = BTree::from_sorted_iter(3, (0..n).map(|i| (i * 2, i)));
+        tree.save(&file.0).unwrap();
+        fs::read(&file.0).unwrap()
+    }
+
+    #[test]
+    fn save_and_reopen_100k_entries() {
+        let file = TempFile::new("100k");
+        saved_tree(&file, 100_000);
+
+        let mapped = BTree::<u64, u64>::open(&file.0).unwrap();
+        assert_eq!(mapped.len(), 100_000);
+        assert_eq!(mapped.degree(), 3);
+
+        // Lookups decode straight from the mapping, without allocating anything
+        let before = allocations();
+        for i in 0..100_000 {
+            assert_eq!(mapped.search(&(i * 2)), Some(i));
+            assert_eq!(mapped.search(&(i * 2 + 1)), None);
+        }
+        assert_eq!(allocations(), before);
+    }
+
+    #[test]
+    fn huge_degree_is_rejected() {
+        let file = TempFile::new("huge-t");
+        let mut bytes = saved_tree(&file, 100);
+
+        for t in [u64::MAX, u64::MAX / 2 + 1, 1 << 62].iter() {
+            bytes[8..16].copy_from_slice(&t.to_le_bytes());
+            fs::write(&file.0, &bytes).unwrap();
+            let err = BTree::<u64, u64>::open(&file.0).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn corrupt_key_count_is_rejected() {
+        let file = TempFile::new("corrupt-n");
+        let mut bytes = saved_tree(&file, 1000);
+
+        // The root is on the leftmost path, which open checks
+        let root = u64::decode(&bytes[40..48]) as usize;
+        bytes[root + 8..root + 16].copy_from_slice(&1000u64.to_le_bytes());
+        fs::write(&file.0, &bytes).unwrap();
+
+        assert!(BTree::<u64, u64>::open(&file.0).is_err());
+    }
+
+    #[test]
+    fn cyclic_child_offset_is_caught() {
+        let file = TempFile::new("cycle");
+        let bytes = saved_tree(&file, 1000);
+        let root = u64::decode(&bytes[40..48]) as usize;
+        // Past the root's leaf flag, n and 2t - 1 slots of a u64 key and value
+        let children = root + 16 + 5 * 16;
+        let n = u64::decode(&bytes[root + 8..root + 16]) as usize;
+
+        // Pointing the leftmost child back at the root is caught when opening
+        let mut leftmost = bytes.clone();
+        leftmost[children..children + 8].copy_from_slice(&(root as u64).to_le_bytes());
+        fs::write(&file.0, &leftmost).unwrap();
+        assert!(BTree::<u64, u64>::open(&file.0).is_err());
+
+        // Pointing the rightmost one back at it only shows up in lookups that take that path
+        let mut rightmost = bytes.clone();
+        let at = children + n * 8;
+        rightmost[at..at + 8].copy_from_slice(&(root as u64).to_le_bytes());
+        fs::write(&file.0, &rightmost).unwrap();
+        let mapped = BTree::<u64, u64>::open(&file.0).unwrap();
+        assert_eq!(mapped.search(&0), Some(0));
+        assert_eq!(mapped.search(&1998), None);
+
+        // So do misaligned and out of bounds offsets
+        for offset in [HEADER_SIZE as u64 + 1, bytes.len() as u64, u64::MAX].iter() {
+            rightmost[at..at + 8].copy_from_slice(&offset.to_le_bytes());
+            fs::write(&file.0, &rightmost).unwrap();
+            let mapped = BTree::<u64, u64>::open(&file.0).unwrap();
+            assert_eq!(mapped.search(&1998), None);
+        }
+    }
+}
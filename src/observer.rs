@@ -0,0 +1,201 @@
+use std::ops::Deref;
+
+use crate::{BTree, Entry, Event};
+
+/// Callbacks for the changes made to an `ObservedBTree`, every one of them does nothing by default
+pub trait Observer<K, P> {
+    fn on_insert(&mut self, _key: &K, _value: &P) {}
+    fn on_remove(&mut self, _key: &K) {}
+    fn on_split(&mut self, _separator: &K) {}
+    fn on_merge(&mut self, _separator: &K) {}
+}
+
+// Lets a caller keep the observer and look at it once the wrapper is gone
+impl<K, P, O> Observer<K, P> for &mut O
+where
+    O: Observer<K, P>,
+{
+    fn on_insert(&mut self, key: &K, value: &P) {
+        (**self).on_insert(key, value)
+    }
+
+    fn on_remove(&mut self, key: &K) {
+        (**self).on_remove(key)
+    }
+
+    fn on_split(&mut self, separator: &K) {
+        (**self).on_split(separator)
+    }
+
+    fn on_merge(&mut self, separator: &K) {
+        (**self).on_merge(separator)
+    }
+}
+
+/// A `BTree` borrowed by an `Observer`, see `BTree::with_observer`.
+/// Only `insert` and `remove` are observed, with the splits and merges they make, in the order they happen.
+/// Read-only methods are reached through `Deref`, and as the wrapper holds the tree's mutable borrow
+/// no other mutation can slip past it. Keys an insert rotates into a sibling under
+/// `SplitStrategy::BStarDeferred`, or a remove borrows from one, aren't splits or merges and aren't reported.
+#[derive(Debug)]
+pub struct ObservedBTree<'a, K, P, O>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+    O: Observer<K, P>,
+{
+    tree: &'a mut BTree<K, P>,
+    observer: O,
+}
+
+impl<K, P, O> ObservedBTree<'_, K, P, O>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+    O: Observer<K, P>,
+{
+    pub fn insert(&mut self, key: K, pointer: P) {
+        self.tree.insert(key, pointer);
+        self.flush_events();
+        self.observer.on_insert(&key, &pointer);
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<Entry<K, P>> {
+        let e = self.tree.remove(key);
+        self.flush_events();
+        if e.is_some() {
+            self.observer.on_remove(key);
+        }
+        e
+    }
+
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+
+    // Hands the splits and merges the last operation made to the observer
+    fn flush_events(&mut self) {
+        let events = self.tree.ctx.events.as_mut().unwrap();
+        for event in events.drain(..) {
+            match event {
+                Event::Split(k) => self.observer.on_split(&k),
+                Event::Merge(k) => self.observer.on_merge(&k),
+            }
+        }
+    }
+}
+
+impl<K, P, O> Deref for ObservedBTree<'_, K, P, O>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+    O: Observer<K, P>,
+{
+    type Target = BTree<K, P>;
+
+    fn deref(&self) -> &BTree<K, P> {
+        self.tree
+    }
+}
+
+// The tree stops recording events once nobody is listening
+impl<K, P, O> Drop for ObservedBTree<'_, K, P, O>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+    O: Observer<K, P>,
+{
+    fn drop(&mut self) {
+        self.tree.ctx.events = None;
+    }
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Borrows the tree so the changes made through the returned wrapper are reported to observer.
+    /// Pass `&mut observer` to keep the observer around after the wrapper is dropped.
+    pub fn with_observer<O: Observer<K, P>>(&mut self, observer: O) -> ObservedBTree<'_, K, P, O> {
+        self.ctx.events = Some(Vec::new());
+        ObservedBTree {
+            tree: self,
+            observer,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Observer;
+    use crate::BTree;
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Call {
+        Insert(u32),
+        Remove(u32),
+        Split(u32),
+        Merge(u32),
+    }
+
+    #[derive(Default)]
+    struct Recorder(Vec<Call>);
+
+    impl Observer<u32, u32> for Recorder {
+        fn on_insert(&mut self, key: &u32, _value: &u32) {
+            self.0.push(Call::Insert(*key));
+        }
+
+        fn on_remove(&mut self, key: &u32) {
+            self.0.push(Call::Remove(*key));
+        }
+
+        fn on_split(&mut self, separator: &u32) {
+            self.0.push(Call::Split(*separator));
+        }
+
+        fn on_merge(&mut self, separator: &u32) {
+            self.0.push(Call::Merge(*separator));
+        }
+    }
+
+    #[test]
+    fn observer_sees_every_change_in_order() {
+        let mut tree = BTree::new(2);
+        let mut recorder = Recorder::default();
+        {
+            let mut observed = tree.with_observer(&mut recorder);
+            for k in 1..=4 {
+                observed.insert(k, k);
+            }
+            observed.remove(&4);
+            observed.remove(&10);
+            observed.remove(&1);
+            assert_eq!(observed.len(), 2);
+        }
+
+        use Call::*;
+        assert_eq!(
+            recorder.0,
+            [
+                Insert(1),
+                Insert(2),
+                Insert(3),
+                Split(2),
+                Insert(4),
+                Remove(4),
+                Merge(2),
+                Remove(1)
+            ]
+        );
+
+        // Once the wrapper is gone the tree doesn't record anymore
+        tree.insert(5, 5);
+        assert!(tree.ctx.events.is_none());
+    }
+}
@@ -0,0 +1,365 @@
+use std::borrow::Borrow;
+use std::ops::{Bound, RangeBounds};
+
+#[cfg(feature = "rand")]
+use rand::Rng;
+
+use crate::{BTree, Entry, Node};
+
+impl<K, P> Node<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    // Returns the entry at in-order position i of this subtree
+    pub fn select(&self, mut i: usize) -> Option<&Entry<K, P>> {
+        if i >= self.size {
+            return None;
+        }
+
+        let mut node = self;
+        'descend: loop {
+            if node.leaf {
+                return node.keys[i].as_ref();
+            }
+
+            // Skip over every child, and the key after it, that lies entirely before position i
+            for j in 0..=node.n {
+                let c = node.child[j].as_ref().unwrap();
+                if i < c.size {
+                    node = c;
+                    continue 'descend;
+                }
+
+                i -= c.size;
+                if i == 0 {
+                    return node.keys[j].as_ref();
+                }
+                i -= 1;
+            }
+
+            return None;
+        }
+    }
+
+    // Like select, but returns the entry mutably
+    pub fn select_mut(&mut self, mut i: usize) -> Option<&mut Entry<K, P>> {
+        if i >= self.size {
+            return None;
+        }
+
+        let mut node = self;
+        'descend: loop {
+            if node.leaf {
+                return node.keys[i].as_mut();
+            }
+
+            for j in 0..=node.n {
+                let size = node.child[j].as_ref().unwrap().size;
+                if i < size {
+                    node = node.child[j].as_mut().unwrap();
+                    continue 'descend;
+                }
+
+                i -= size;
+                if i == 0 {
+                    return node.keys[j].as_mut();
+                }
+                i -= 1;
+            }
+
+            return None;
+        }
+    }
+
+    // Counts the entries whose key is below key, or equal to it when inclusive is set
+    pub fn count_below<Q>(&self, key: &Q, inclusive: bool) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut count = 0;
+        let mut node = self;
+        loop {
+            let mut i = 0;
+            while i < node.n {
+                let k = node.keys[i].as_ref().unwrap().key.borrow();
+                if k > key || (k == key && !inclusive) {
+                    break;
+                }
+
+                // The key and everything to the left of it counts
+                count += 1;
+                if !node.leaf {
+                    count += node.child[i].as_ref().unwrap().size;
+                }
+                i += 1;
+            }
+
+            if node.leaf {
+                return count;
+            }
+            node = node.child[i].as_ref().unwrap();
+        }
+    }
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Returns the entry at in-order position i, in O(height)
+    pub fn select(&self, i: usize) -> Option<&Entry<K, P>> {
+        self.root.as_ref()?.select(i)
+    }
+
+    /// Returns the number of keys smaller than key, which is the position key has or would have in key order
+    pub fn rank(&self, key: &K) -> usize {
+        match &self.root {
+            Some(r) => r.count_below(key, false),
+            None => 0,
+        }
+    }
+
+    /// Returns the positions of the entries with the given key, as the half-open range `first..last`
+    /// in key order, like C++'s `equal_range`. The range is empty, at the position the key would have, if it's absent.
+    /// `insert` doesn't reject duplicate keys, so `last - first` can be larger than one.
+    pub fn equal_range(&self, key: &K) -> (usize, usize) {
+        match &self.root {
+            Some(r) => (r.count_below(key, false), r.count_below(key, true)),
+            None => (0, 0),
+        }
+    }
+
+    /// Counts the keys strictly between lo and hi from two rank queries, in O(height).
+    /// Returns 0 if lo isn't below hi.
+    pub fn count_between(&self, lo: &K, hi: &K) -> usize {
+        match &self.root {
+            Some(r) => r
+                .count_below(hi, false)
+                .saturating_sub(r.count_below(lo, true)),
+            None => 0,
+        }
+    }
+
+    /// Counts the entries in range in O(height), without visiting them
+    pub fn keys_in_range_count<R: RangeBounds<K>>(&self, range: R) -> usize {
+        self.range_count(&range)
+    }
+
+    pub(crate) fn range_count<Q, R>(&self, range: &R) -> usize
+    where
+        K: Borrow<Q>,
+        R: RangeBounds<Q>,
+        Q: Ord + ?Sized,
+    {
+        let root = match &self.root {
+            Some(r) => r,
+            None => return 0,
+        };
+
+        let low = match range.start_bound() {
+            Bound::Included(s) => root.count_below(s, false),
+            Bound::Excluded(s) => root.count_below(s, true),
+            Bound::Unbounded => 0,
+        };
+        let high = match range.end_bound() {
+            Bound::Included(e) => root.count_below(e, true),
+            Bound::Excluded(e) => root.count_below(e, false),
+            Bound::Unbounded => root.size,
+        };
+
+        high.saturating_sub(low)
+    }
+
+    /// Splits the key space into k contiguous ranges holding about `len / k` entries each,
+    /// e.g. to process a tree in parallel. The ranges cover every possible key.
+    /// If there are fewer entries than k, fewer ranges are returned so none of them are empty.
+    pub fn partition(&self, k: usize) -> Vec<(Bound<K>, Bound<K>)> {
+        if k == 0 {
+            panic!("Can't partition into 0 ranges");
+        }
+
+        // Every range after the first starts at the entry at its share of the positions
+        let mut boundaries: Vec<K> = (1..k)
+            .map(|i| i * self.len() / k)
+            .filter(|&pos| pos > 0)
+            .filter_map(|pos| self.select(pos))
+            .map(|e| e.key)
+            .collect();
+        boundaries.dedup();
+
+        let mut ranges = Vec::with_capacity(boundaries.len() + 1);
+        let mut start = Bound::Unbounded;
+        for b in boundaries {
+            ranges.push((start, Bound::Excluded(b)));
+            start = Bound::Included(b);
+        }
+        ranges.push((start, Bound::Unbounded));
+
+        ranges
+    }
+
+    /// Returns the entry at the q-th quantile, where 0.0 is the smallest key and 1.0 the largest
+    pub fn quantile(&self, q: f64) -> Option<&Entry<K, P>> {
+        if !(0.0..=1.0).contains(&q) {
+            panic!("Quantile has to be between 0.0 and 1.0");
+        }
+
+        if self.is_empty() {
+            return None;
+        }
+
+        self.select((q * (self.len() - 1) as f64).round() as usize)
+    }
+
+    /// Returns the entry at the p-th percentile, at position `len * p`, e.g. the median for 0.5.
+    /// p = 1.0 gives the largest key. Takes O(height) through the subtree sizes.
+    pub fn percentile(&self, p: f64) -> Option<(&K, &P)> {
+        if !(0.0..=1.0).contains(&p) {
+            panic!("Percentile has to be between 0.0 and 1.0");
+        }
+
+        if self.is_empty() {
+            return None;
+        }
+
+        let i = ((self.len() as f64 * p) as usize).min(self.len() - 1);
+        self.select(i).map(|e| (&e.key, &e.value))
+    }
+
+    /// Picks n distinct entries uniformly at random, without replacement, and returns them in key order.
+    /// The ranks are sampled and sorted first, so the entries are collected in a single in-order scan
+    /// that skips the subtrees in between. Returns every entry if there are fewer than n.
+    /// Only available with the `rand` feature.
+    #[cfg(feature = "rand")]
+    pub fn random_sample(&self, n: usize, rng: &mut impl Rng) -> Vec<(&K, &P)> {
+        let n = n.min(self.len());
+        let mut ranks = rand::seq::index::sample(rng, self.len(), n).into_vec();
+        ranks.sort_unstable();
+
+        let mut iter = self.iter();
+        let mut next = 0;
+        let mut sample = Vec::with_capacity(n);
+        for r in ranks {
+            let e = iter.nth(r - next).unwrap();
+            sample.push((&e.key, &e.value));
+            next = r + 1;
+        }
+        sample
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use crate::tests::lcg;
+    use crate::BTree;
+
+    #[test]
+    fn quantile_bounds_and_median() {
+        let tree = BTree::from_sorted_iter(3, (0..1001u32).map(|i| (i, i)));
+        assert_eq!(tree.quantile(0.0).map(|e| e.key), Some(0));
+        assert_eq!(tree.quantile(0.5).map(|e| e.key), Some(500));
+        assert_eq!(tree.quantile(1.0).map(|e| e.key), Some(1000));
+
+        let tree = BTree::from_sorted_iter(3, (0..1000u32).map(|i| (i, i)));
+        assert_eq!(tree.quantile(0.5).map(|e| e.key), Some(500));
+        assert_eq!(tree.quantile(1.0).map(|e| e.key), Some(999));
+
+        let empty: BTree<u32, u32> = BTree::new(3);
+        assert!(empty.quantile(0.5).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Quantile has to be between 0.0 and 1.0")]
+    fn quantile_out_of_bounds() {
+        let tree = BTree::from_sorted_iter(3, (0..10u32).map(|i| (i, i)));
+        tree.quantile(1.5);
+    }
+
+    #[test]
+    fn partition_into_equal_contiguous_ranges() {
+        let tree = BTree::from_sorted_iter(3, (0..1000u32).map(|i| (i, i)));
+        let ranges = tree.partition(4);
+        assert_eq!(ranges.len(), 4);
+
+        // Each range starts where the one before it ended, from the lowest to the highest key
+        assert_eq!(ranges[0].0, Bound::Unbounded);
+        assert_eq!(ranges[3].1, Bound::Unbounded);
+        for w in ranges.windows(2) {
+            match (w[0].1, w[1].0) {
+                (Bound::Excluded(end), Bound::Included(start)) => assert_eq!(end, start),
+                bounds => panic!("Ranges aren't contiguous: {:?}", bounds),
+            }
+        }
+
+        for range in ranges.iter() {
+            assert_eq!(tree.range(*range).count(), 250);
+        }
+
+        // Too few entries to fill every range
+        let small = BTree::from_sorted_iter(3, (0..3u32).map(|i| (i, i)));
+        let ranges = small.partition(8);
+        assert_eq!(ranges.len(), 3);
+        assert!(ranges.iter().all(|r| small.range(*r).count() == 1));
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn random_sample_picks_distinct_entries_in_key_order() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let tree = BTree::from_sorted_iter(3, (0..1000u32).map(|i| (i, i * 2)));
+        let mut rng = StdRng::seed_from_u64(188);
+
+        let sample = tree.random_sample(100, &mut rng);
+        assert_eq!(sample.len(), 100);
+        assert!(sample.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(sample.iter().all(|&(k, v)| *v == k * 2));
+
+        assert_eq!(tree.random_sample(5000, &mut rng).len(), 1000);
+        assert!(tree.random_sample(0, &mut rng).is_empty());
+    }
+
+    #[test]
+    fn count_between_matches_a_scan() {
+        let mut tree = BTree::new(3);
+        let mut seed = 199;
+        for _ in 0..3000 {
+            let key = (lcg(&mut seed) % 1000) as u32;
+            tree.insert(key, key);
+        }
+
+        let mut pairs = vec![
+            (0, 999),
+            (0, 0),
+            (500, 500),
+            (500, 501),
+            (999, 0),
+            (2000, 3000),
+            (0, 5000),
+        ];
+        for _ in 0..200 {
+            pairs.push((
+                (lcg(&mut seed) % 1100) as u32,
+                (lcg(&mut seed) % 1100) as u32,
+            ));
+        }
+
+        for (lo, hi) in pairs {
+            let naive = tree.iter().filter(|e| lo < e.key && e.key < hi).count();
+            assert_eq!(
+                tree.count_between(&lo, &hi),
+                naive,
+                "Between {} and {}",
+                lo,
+                hi
+            );
+        }
+        assert_eq!(BTree::<u32, u32>::new(2).count_between(&0, &10), 0);
+    }
+}
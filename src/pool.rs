@@ -0,0 +1,118 @@
+use crate::Node;
+
+/// Free-list of emptied nodes, handed back out when a new node is needed instead of allocating one
+#[derive(Debug)]
+pub struct NodePool<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    enabled: bool,
+    free: Vec<Node<K, P>>,
+}
+
+impl<K, P> NodePool<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    pub fn new(enabled: bool) -> NodePool<K, P> {
+        NodePool {
+            enabled,
+            free: Vec::new(),
+        }
+    }
+
+    pub fn take(&mut self, t: usize, leaf: bool) -> Node<K, P> {
+        match self.free.pop() {
+            Some(mut node) => {
+                node.n = 0;
+                node.size = 0;
+                node.leaf = leaf;
+                node
+            }
+            None => Node::new(t, leaf),
+        }
+    }
+
+    // Nodes have to come back with every key and child slot emptied
+    pub fn give(&mut self, node: Node<K, P>) {
+        debug_assert!(node.keys.iter().all(|k| k.is_none()));
+        debug_assert!(node.child.iter().all(|c| c.is_none()));
+
+        if self.enabled {
+            self.free.push(node);
+        }
+    }
+
+    // Empties every node of a subtree that's thrown away and keeps them for reuse
+    pub fn give_tree(&mut self, mut node: Node<K, P>) {
+        for slot in node.child.iter_mut() {
+            if let Some(c) = slot.take() {
+                self.give_tree(c);
+            }
+        }
+        for slot in node.keys.iter_mut() {
+            *slot = None;
+        }
+        self.give(node);
+    }
+
+    // Drops free nodes until at most len are left
+    pub fn truncate(&mut self, len: usize) {
+        self.free.truncate(len);
+    }
+
+    pub fn len(&self) -> usize {
+        self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.free.is_empty()
+    }
+
+    // Bytes held by the free nodes, including the free-list itself
+    pub fn heap_size(&self) -> usize {
+        let nodes: usize = self.free.iter().map(|n| n.heap_size()).sum();
+        nodes + self.free.capacity() * std::mem::size_of::<Node<K, P>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{allocations, assert_valid, lcg};
+    use crate::BTree;
+
+    // Allocations made by a steady insert/remove churn on a tree of about 2000 keys
+    fn churn_allocations(mut tree: BTree<u64, u64>) -> usize {
+        let mut seed = 160;
+        for i in 0..2000 {
+            tree.insert(i * 2, i);
+        }
+
+        let before = allocations();
+        for _ in 0..20_000 {
+            let key = lcg(&mut seed) % 4000;
+            if tree.remove(&key).is_none() {
+                tree.insert(key, key);
+            }
+        }
+        let after = allocations();
+
+        assert_valid(&tree);
+        after - before
+    }
+
+    #[test]
+    fn recycler_cuts_allocations_under_churn() {
+        let plain = churn_allocations(BTree::new(2));
+        let recycled = churn_allocations(BTree::with_node_recycler(2));
+
+        assert!(
+            recycled * 10 < plain,
+            "{} allocations with the recycler, {} without",
+            recycled,
+            plain
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use std::ops::{Deref, RangeBounds};
+
+use crate::{after_end, before_start, covers, BTree, Entry, Node};
+
+// Minimum value of a subtree, mirroring the shape of the node it belongs to
+#[derive(Debug)]
+struct MinNode<P> {
+    min: P,
+    children: Vec<MinNode<P>>,
+}
+
+impl<P: Copy + Ord> MinNode<P> {
+    fn build<K: Eq + Ord + Copy>(node: &Node<K, P>) -> MinNode<P> {
+        let mut children = Vec::new();
+        if !node.leaf {
+            for i in 0..=node.n {
+                children.push(MinNode::build(node.child[i].as_ref().unwrap()));
+            }
+        }
+
+        MinNode {
+            min: MinNode::node_min(node, &children),
+            children,
+        }
+    }
+
+    // Smallest value among the keys of node and the minimums of its children
+    fn node_min<K: Eq + Ord + Copy>(node: &Node<K, P>, children: &[MinNode<P>]) -> P {
+        let keys = node.keys[..node.n]
+            .iter()
+            .map(|e| e.as_ref().unwrap().value);
+        keys.chain(children.iter().map(|c| c.min)).min().unwrap()
+    }
+
+    // Recomputes the minimums along the path an insert of key took, when nothing but that path changed.
+    // Like `insert_non_full` it passes keys equal to key, the new entry always lands in a leaf.
+    fn refresh_path<K: Eq + Ord + Copy>(&mut self, node: &Node<K, P>, key: &K) {
+        if !node.leaf {
+            let i = node.iter_keys().take_while(|k| *k <= key).count();
+            self.children[i].refresh_path(node.child[i].as_ref().unwrap(), key);
+        }
+        self.min = MinNode::node_min(node, &self.children);
+    }
+}
+
+/// A `BTree` that additionally keeps the minimum value of every subtree, so `range_min` can skip whole subtrees.
+/// Read-only methods are reached through `Deref`, mutations go through the wrapper to keep the minimums up to date.
+#[derive(Debug)]
+pub struct RangeMinBTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy + Ord,
+{
+    tree: BTree<K, P>,
+    mins: Option<MinNode<P>>,
+}
+
+impl<K, P> RangeMinBTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy + Ord,
+{
+    pub fn new(t: usize) -> RangeMinBTree<K, P> {
+        let mut tree = BTree::new(t);
+        tree.ctx.events = Some(Vec::new());
+        RangeMinBTree { tree, mins: None }
+    }
+
+    /// Inserts like `BTree::insert`. Without a split only the minimums along the path to the key change,
+    /// a split rebuilds them in O(n).
+    pub fn insert(&mut self, key: K, pointer: P) {
+        self.tree.insert(key, pointer);
+
+        let events = self.tree.ctx.events.as_mut().unwrap();
+        let restructured = !events.is_empty();
+        events.clear();
+
+        match (&mut self.mins, &self.tree.root) {
+            (Some(m), Some(r)) if !restructured => m.refresh_path(r, &key),
+            _ => self.rebuild(),
+        }
+    }
+
+    /// Removes like `BTree::remove`. Removals can move keys between siblings, even when the key is absent,
+    /// so the minimums are rebuilt in O(n).
+    pub fn remove(&mut self, key: &K) -> Option<Entry<K, P>> {
+        let e = self.tree.remove(key);
+        self.tree.ctx.events.as_mut().unwrap().clear();
+        self.rebuild();
+        e
+    }
+
+    /// Returns the smallest value among the entries in range.
+    /// Subtrees that lie entirely inside the range contribute their stored minimum,
+    /// and subtrees whose minimum can't beat the best value found so far are skipped.
+    pub fn range_min<R: RangeBounds<K>>(&self, range: R) -> Option<&P> {
+        let mut best = None;
+        if let (Some(r), Some(m)) = (&self.tree.root, &self.mins) {
+            RangeMinBTree::query(r, m, &range, None, None, &mut best);
+        }
+        best
+    }
+
+    fn rebuild(&mut self) {
+        self.mins = self.tree.root.as_ref().map(MinNode::build);
+    }
+
+    // lo and hi are the separators around this subtree in its ancestors, every key in it lies between them
+    fn query<'a, R: RangeBounds<K>>(
+        node: &'a Node<K, P>,
+        mins: &'a MinNode<P>,
+        range: &R,
+        lo: Option<&K>,
+        hi: Option<&K>,
+        best: &mut Option<&'a P>,
+    ) {
+        if best.is_some_and(|b| mins.min >= *b) {
+            return;
+        }
+        if covers(range, lo, hi) {
+            *best = Some(&mins.min);
+            return;
+        }
+
+        for i in 0..=node.n {
+            let key = if i < node.n {
+                node.keys[i].as_ref()
+            } else {
+                None
+            };
+
+            if !node.leaf {
+                let child_lo = if i > 0 {
+                    Some(node.keys[i - 1].as_ref().unwrap().get_key())
+                } else {
+                    lo
+                };
+                if child_lo.is_some_and(|k| after_end(range, k)) {
+                    return;
+                }
+
+                let child_hi = key.map(|e| e.get_key()).or(hi);
+                if !child_hi.is_some_and(|k| before_start(range, k)) {
+                    let c = node.child[i].as_ref().unwrap();
+                    RangeMinBTree::query(c, &mins.children[i], range, child_lo, child_hi, best);
+                }
+            }
+
+            if let Some(e) = key {
+                if range.contains(e.get_key()) && best.is_none_or(|b| e.value < *b) {
+                    *best = Some(&e.value);
+                }
+            }
+        }
+    }
+}
+
+impl<K, P> Deref for RangeMinBTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy + Ord,
+{
+    type Target = BTree<K, P>;
+
+    fn deref(&self) -> &BTree<K, P> {
+        &self.tree
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound::{self, Excluded, Included, Unbounded};
+
+    use super::RangeMinBTree;
+    use crate::tests::lcg;
+
+    fn brute_force(tree: &RangeMinBTree<u32, u32>, range: (Bound<u32>, Bound<u32>)) -> Option<u32> {
+        use std::ops::RangeBounds;
+        tree.iter()
+            .filter(|e| range.contains(&e.key))
+            .map(|e| e.value)
+            .min()
+    }
+
+    #[test]
+    fn range_min_matches_brute_force_with_duplicates() {
+        let mut tree = RangeMinBTree::new(2);
+        let mut seed = 175;
+        for step in 0..3000 {
+            // Few distinct keys, so most of them have duplicates
+            let key = (lcg(&mut seed) % 40) as u32;
+            if lcg(&mut seed) % 4 == 0 {
+                tree.remove(&key);
+            } else {
+                tree.insert(key, (lcg(&mut seed) % 10_000) as u32);
+            }
+
+            if step % 20 == 0 {
+                for a in 0..42 {
+                    let b = a + (lcg(&mut seed) % 12) as u32;
+                    let bounds = [
+                        (Included(a), Excluded(b)),
+                        (Excluded(a), Included(b)),
+                        (Excluded(a), Excluded(b)),
+                        (Included(a), Included(b)),
+                        (Unbounded, Excluded(a)),
+                        (Excluded(a), Unbounded),
+                    ];
+                    for range in bounds.iter() {
+                        let expected = brute_force(&tree, *range);
+                        assert_eq!(
+                            tree.range_min(*range).copied(),
+                            expected,
+                            "{:?} at step {}",
+                            range,
+                            step
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
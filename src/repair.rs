@@ -0,0 +1,199 @@
+use crate::{BTree, Entry, Node};
+
+/// What `BTree::repair` found wrong and fixed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepairReport<K> {
+    /// Keys dropped because they were smaller than the key before them, in traversal order
+    pub dropped_keys: Vec<K>,
+    /// Key or child slots that should have held something but were empty
+    pub missing_slots: usize,
+    /// Nodes holding fewer than `t - 1` or more than `2t - 1` keys
+    pub misshapen_nodes: usize,
+    /// Nodes whose stored subtree size didn't match their entries
+    pub size_mismatches: usize,
+    /// Whether the leaves were at different depths
+    pub uneven_leaves: bool,
+    /// Whether the tree was rebuilt, which happens whenever anything else was found
+    pub rebuilt: bool,
+}
+
+impl<K> RepairReport<K> {
+    pub fn is_clean(&self) -> bool {
+        !self.rebuilt
+    }
+}
+
+impl<K, P> Node<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    // Collects the entries of this subtree in traversal order, without trusting n, size or the slots.
+    // Keys that break the order are dropped, equal keys are duplicates and are kept,
+    // everything else that's off is counted in the report.
+    // Returns the number of entries actually below this node.
+    fn salvage(
+        &self,
+        root: bool,
+        depth: usize,
+        leaf_depth: &mut Option<usize>,
+        kept: &mut Vec<Entry<K, P>>,
+        report: &mut RepairReport<K>,
+    ) -> usize {
+        let n = self.n.min(self.keys.len());
+        if n != self.n || n > 2 * self.t - 1 || (!root && n < self.t - 1) {
+            report.misshapen_nodes += 1;
+        }
+
+        if self.leaf && *leaf_depth.get_or_insert(depth) != depth {
+            report.uneven_leaves = true;
+        }
+
+        let mut count = 0;
+        for i in 0..=n {
+            if !self.leaf {
+                match self.child.get(i).and_then(|c| c.as_ref()) {
+                    Some(c) => count += c.salvage(false, depth + 1, leaf_depth, kept, report),
+                    None => report.missing_slots += 1,
+                }
+            }
+
+            if i == n {
+                break;
+            }
+            match self.keys[i] {
+                Some(e) if kept.last().is_none_or(|last| last.key <= e.key) => {
+                    kept.push(e);
+                    count += 1;
+                }
+                Some(e) => {
+                    report.dropped_keys.push(e.key);
+                    count += 1;
+                }
+                None => report.missing_slots += 1,
+            }
+        }
+
+        if count != self.size {
+            report.size_mismatches += 1;
+        }
+        count
+    }
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Checks the whole tree and, if anything violates the invariants, rebuilds it from the entries that can be salvaged,
+    /// e.g. after manual manipulation of the nodes or loading corrupt data.
+    /// Entries are read in traversal order, keys that are smaller than the key before them are dropped,
+    /// and everything else found is counted in the report. The rebuild leaves every node valid and packed.
+    ///
+    /// Both the check and the rebuild are O(n): a damaged node can hold entries that belong in other
+    /// subtrees, so the whole tree is rebuilt from the salvaged entries rather than only the damaged parts.
+    pub fn repair(&mut self) -> RepairReport<K> {
+        let mut report = RepairReport {
+            dropped_keys: Vec::new(),
+            missing_slots: 0,
+            misshapen_nodes: 0,
+            size_mismatches: 0,
+            uneven_leaves: false,
+            rebuilt: false,
+        };
+
+        let mut kept = Vec::new();
+        if let Some(r) = &self.root {
+            r.salvage(true, 0, &mut None, &mut kept, &mut report);
+        }
+
+        report.rebuilt = !report.dropped_keys.is_empty()
+            || report.missing_slots > 0
+            || report.misshapen_nodes > 0
+            || report.size_mismatches > 0
+            || report.uneven_leaves;
+
+        if report.rebuilt {
+            self.root = Node::load_sorted(self.t, kept, 2 * self.t - 1);
+            self.refresh_extremes();
+            self.forget_removed_hits();
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{assert_valid, keys};
+    use crate::BTree;
+
+    fn tree() -> BTree<u32, u32> {
+        let mut tree = BTree::new(2);
+        for i in 0..200 {
+            tree.insert(i, i);
+        }
+        tree
+    }
+
+    #[test]
+    fn repair_leaves_a_valid_tree_alone() {
+        let mut tree = tree();
+        for i in 0..50 {
+            tree.insert(i * 3, 0);
+        }
+
+        // Duplicates are fine
+        assert!(tree.repair().is_clean());
+        assert_eq!(tree.len(), 250);
+    }
+
+    #[test]
+    fn repair_drops_out_of_order_keys() {
+        let mut tree = tree();
+
+        // Swap the first two keys in the rightmost leaf, so the smaller one comes second
+        let mut node = tree.root.as_mut().unwrap();
+        while !node.leaf {
+            node = node.child[node.n].as_mut().unwrap();
+        }
+        assert!(node.n >= 2);
+        let smaller = node.keys[0].unwrap().key;
+        node.keys.swap(0, 1);
+
+        let report = tree.repair();
+        assert!(report.rebuilt);
+        assert_eq!(report.dropped_keys, [smaller]);
+        assert_valid(&tree);
+        assert_eq!(
+            keys(&tree),
+            (0..200).filter(|&k| k != smaller).collect::<Vec<u32>>()
+        );
+    }
+
+    #[test]
+    fn repair_fixes_sizes_and_missing_slots() {
+        let mut tree = tree();
+        tree.root.as_mut().unwrap().size += 7;
+        let report = tree.repair();
+        assert_eq!(report.size_mismatches, 1);
+        assert_valid(&tree);
+        assert_eq!(tree.len(), 200);
+
+        // Empty a key slot that n still counts, the entry in it is lost
+        let mut node = tree.root.as_mut().unwrap();
+        while !node.leaf {
+            node = node.child[node.n].as_mut().unwrap();
+        }
+        let lost = node.keys[0].take().unwrap().key;
+
+        let report = tree.repair();
+        assert_eq!(report.missing_slots, 1);
+        assert!(report.size_mismatches > 0);
+        assert_valid(&tree);
+        assert_eq!(tree.len(), 199);
+        assert_eq!(tree.get(&lost), None);
+        assert!(tree.repair().is_clean());
+    }
+}
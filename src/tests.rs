@@ -0,0 +1,869 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use crate::BTree;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+// Counts the allocations of each thread, so tests running in parallel don't see each other's
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// Allocations the current thread has made so far
+pub(crate) fn allocations() -> usize {
+    ALLOCATIONS.with(|a| a.get())
+}
+
+// Small deterministic generator, so failing tests can be replayed
+pub(crate) fn lcg(seed: &mut u64) -> u64 {
+    *seed = seed
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    *seed >> 33
+}
+
+// Panics unless the tree is a valid B-tree: leaves at the same depth, every node within its key bounds,
+// stored sizes matching, keys strictly increasing and the cached extremes up to date
+pub(crate) fn assert_valid<K, P>(tree: &BTree<K, P>)
+where
+    K: Eq + Ord + Copy + std::fmt::Debug,
+    P: Copy,
+{
+    assert!(tree.is_balanced(), "Leaves are at different depths");
+    tree.validate_sizes().unwrap();
+    tree.assert_key_order();
+
+    for (depth, level) in tree.level_iter().enumerate() {
+        for node in level {
+            assert!(node.n < 2 * tree.t, "Node holds {} keys", node.n);
+            assert!(
+                depth == 0 || node.n >= tree.t - 1,
+                "Node holds {} keys",
+                node.n
+            );
+        }
+    }
+
+    assert_eq!(tree.min(), tree.iter().next().map(|e| &e.key));
+    assert_eq!(tree.max(), tree.iter().next_back().map(|e| &e.key));
+}
+
+// Keys of the tree in order
+pub(crate) fn keys<K, P>(tree: &BTree<K, P>) -> Vec<K>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    tree.iter().map(|e| e.key).collect()
+}
+
+#[test]
+fn retain_range_only_filters_inside_the_range() {
+    let mut tree = BTree::new(3);
+    for i in 0..1000u32 {
+        tree.insert(i, i);
+    }
+
+    tree.retain_range(200..300, |k, _| k % 2 == 0);
+
+    assert_valid(&tree);
+    let expected: Vec<u32> = (0..1000)
+        .filter(|k| !(200..300).contains(k) || k % 2 == 0)
+        .collect();
+    assert_eq!(keys(&tree), expected);
+    assert_eq!(tree.len(), 950);
+}
+
+thread_local! {
+    static COMPARISONS: Cell<usize> = const { Cell::new(0) };
+}
+
+// String key that counts how often it's compared. Keys have to be Copy, so it borrows a leaked string
+// instead of owning a String.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CountedKey(&'static str);
+
+impl Ord for CountedKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        COMPARISONS.with(|c| c.set(c.get() + 1));
+        self.0.cmp(other.0)
+    }
+}
+
+impl PartialOrd for CountedKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[test]
+fn search_compares_each_key_once() {
+    let mut tree = BTree::new(3);
+    let keys: Vec<CountedKey> = (0..2000)
+        .map(|i| CountedKey(Box::leak(format!("key-{:05}", i * 2).into_boxed_str())))
+        .collect();
+    for (i, &k) in keys.iter().enumerate() {
+        tree.insert(k, i);
+    }
+
+    let absent = CountedKey(Box::leak(String::from("key-00101").into_boxed_str()));
+    for key in keys.iter().step_by(7).chain([&absent]) {
+        // Every key the search passes, and the one it stops at, is compared exactly once
+        let mut expected = 0;
+        let mut node = tree.root.as_ref().unwrap();
+        loop {
+            let i = node.iter_keys().take_while(|k| k.0 < key.0).count();
+            expected += (i + 1).min(node.n);
+            let found = i < node.n && node.keys[i].as_ref().unwrap().key.0 == key.0;
+            if found || node.leaf {
+                break;
+            }
+            node = node.child[i].as_ref().unwrap();
+        }
+
+        COMPARISONS.with(|c| c.set(0));
+        let found = tree.root.as_ref().unwrap().search(key, false).is_some();
+        assert_eq!(found, key != &absent);
+        assert_eq!(COMPARISONS.with(|c| c.get()), expected);
+    }
+}
+
+// Rock-paper-scissors key, every key is beaten by the next one around so the ordering isn't transitive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Hand(u8);
+
+impl Ord for Hand {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        match (other.0 + 3 - self.0) % 3 {
+            0 => std::cmp::Ordering::Equal,
+            1 => std::cmp::Ordering::Less,
+            _ => std::cmp::Ordering::Greater,
+        }
+    }
+}
+
+impl PartialOrd for Hand {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[test]
+#[cfg(debug_assertions)]
+fn checked_tree_panics_at_the_intransitive_insert() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut tree = BTree::checked(2);
+    tree.insert(Hand(0), ());
+    tree.insert(Hand(1), ());
+
+    // Hand(2) beats Hand(1) but loses to Hand(0), it can't be placed consistently
+    let result = catch_unwind(AssertUnwindSafe(|| tree.insert(Hand(2), ())));
+    assert!(result.is_err());
+}
+
+#[test]
+fn checked_tree_accepts_consistent_keys() {
+    let mut tree = BTree::checked(2);
+    let mut seed = 156;
+    for _ in 0..2000 {
+        // Plenty of duplicates, which are fine
+        tree.insert(lcg(&mut seed) % 300, ());
+    }
+
+    // assert_valid wants strictly increasing keys, so check the shape and order separately
+    assert!(tree.is_balanced());
+    tree.validate_sizes().unwrap();
+    assert!(keys(&tree).windows(2).all(|w| w[0] <= w[1]));
+    assert_eq!(tree.len(), 2000);
+}
+
+#[test]
+fn suggested_degree_keeps_the_height() {
+    for &n in [0usize, 1, 3, 10, 100, 1000, 12345, 100_000].iter() {
+        for height in 1..5 {
+            let t = crate::suggest_degree(n, height);
+            // One less would allow a tree that's too high
+            if t > 2 {
+                assert!(2 * (t - 1).pow(height as u32) - 1 <= n);
+            }
+            if t > 3000 {
+                continue;
+            }
+
+            // Ascending inserts leave nodes half full, random ones a bit fuller
+            let mut ascending = BTree::new(t);
+            let mut random = BTree::new(t);
+            let mut seed = 157;
+            for i in 0..n {
+                ascending.insert(i, ());
+                random.insert(lcg(&mut seed), ());
+            }
+            let heights = (ascending.level_iter().count(), random.level_iter().count());
+            assert!(
+                heights.0 <= height && heights.1 <= height,
+                "n = {}, height = {}, t = {}",
+                n,
+                height,
+                t
+            );
+        }
+    }
+}
+
+#[test]
+fn debug_prints_entries_compactly() {
+    let mut tree = BTree::new(2);
+    for i in 0..5u32 {
+        tree.insert(i, i * 10);
+    }
+    assert_eq!(
+        format!("{:?}", tree),
+        "BTree { t: 2, len: 5, entries: [(0, 0), (1, 10), (2, 20), (3, 30), (4, 40)] }"
+    );
+
+    for i in 5..100u32 {
+        tree.insert(i, i * 10);
+    }
+    let printed = format!("{:?}", tree);
+    assert!(printed.starts_with("BTree { t: 2, len: 100, entries: [(0, 0), (1, 10), "));
+    assert!(printed.ends_with(", ..] }"));
+
+    // The raw structure is still there, empty slots and all
+    assert!(tree.debug_structure().contains("None"));
+}
+
+#[test]
+fn batch_membership_matches_per_key_lookups() {
+    let mut tree = BTree::new(3);
+    for i in 0..500u64 {
+        tree.insert(i * 3, i);
+    }
+
+    let mut seed = 165;
+    for round in 0..200 {
+        let len = round % 12;
+        // Unsorted batches with duplicates, mostly multiples of 3 so some are all present
+        let batch: Vec<u64> = (0..len)
+            .map(|_| {
+                let k = lcg(&mut seed) % 1600;
+                if round % 3 == 0 {
+                    k - k % 3
+                } else {
+                    k
+                }
+            })
+            .collect();
+
+        let naive_all = batch.iter().all(|k| tree.get(k).is_some());
+        let naive_any = batch.iter().any(|k| tree.get(k).is_some());
+        assert_eq!(tree.contains_all(&batch), naive_all, "{:?}", batch);
+        assert_eq!(tree.contains_any(&batch), naive_any, "{:?}", batch);
+    }
+
+    assert!(tree.contains_all(&[]));
+    assert!(!tree.contains_any(&[]));
+}
+
+#[test]
+fn cached_extremes_follow_inserts_and_removes() {
+    let mut order: Vec<u32> = (0..1000).collect();
+    let mut seed = 167;
+    for i in (1..order.len()).rev() {
+        order.swap(i, lcg(&mut seed) as usize % (i + 1));
+    }
+
+    let mut tree = BTree::new(3);
+    for &k in order.iter() {
+        tree.insert(k, k);
+    }
+    // Straight from the cache, without a descent
+    assert_eq!(tree.min_key, Some(0));
+    assert_eq!(tree.max_key, Some(999));
+    assert_eq!((tree.min(), tree.max()), (Some(&0), Some(&999)));
+
+    // Removing an extreme descends once to find the next one
+    tree.remove(&0);
+    tree.remove(&999);
+    tree.remove(&500);
+    assert_eq!((tree.min(), tree.max()), (Some(&1), Some(&998)));
+
+    tree.insert(1, 1);
+    tree.remove(&1);
+    assert_eq!(tree.min(), Some(&1), "A duplicate of the minimum is left");
+
+    for k in order.iter() {
+        tree.remove(k);
+        assert_valid(&tree);
+    }
+    assert_eq!((tree.min(), tree.max()), (None, None));
+}
+
+// Random mutations checked against a counting model, with the sizes verified after every one of them
+fn check_against_model(strategy: crate::SplitStrategy, seed: u64) {
+    use std::collections::BTreeMap;
+
+    let mut tree = BTree::new(2);
+    tree.set_split_strategy(strategy);
+    let mut model: BTreeMap<u32, usize> = BTreeMap::new();
+    let mut seed = seed;
+
+    for step in 0..5000 {
+        let key = (lcg(&mut seed) % 600) as u32;
+        match lcg(&mut seed) % 11 {
+            0..=3 => {
+                tree.insert(key, key);
+                *model.entry(key).or_insert(0) += 1;
+            }
+            4 => {
+                tree.insert_ranked(key, key);
+                *model.entry(key).or_insert(0) += 1;
+            }
+            5..=7 => {
+                let removed = if step % 2 == 0 {
+                    tree.remove(&key).is_some()
+                } else {
+                    tree.remove_lazy(&key).is_some()
+                };
+                assert_eq!(removed, model.contains_key(&key));
+                if let Some(count) = model.get_mut(&key) {
+                    *count -= 1;
+                    if *count == 0 {
+                        model.remove(&key);
+                    }
+                }
+            }
+            8 => {
+                tree.retain_range(key..key + 20, |k, _| k % 2 == 0);
+                model.retain(|k, _| !(key..key + 20).contains(k) || k % 2 == 0);
+            }
+            9 => {
+                tree.take_range(key..key + 5);
+                model.retain(|k, _| !(key..key + 5).contains(k));
+            }
+            _ => {
+                tree.clear_range(key..=key + 30);
+                model.retain(|k, _| !(key..=key + 30).contains(k));
+            }
+        }
+
+        if let Err(e) = tree.validate_sizes() {
+            panic!("Size mismatch after step {}: {}", step, e);
+        }
+        assert!(tree.is_balanced());
+        let expected: Vec<u32> = model
+            .iter()
+            .flat_map(|(&k, &count)| std::iter::repeat_n(k, count))
+            .collect();
+        assert_eq!(keys(&tree), expected, "Keys differ after step {}", step);
+    }
+}
+
+#[test]
+fn random_operations_match_the_model() {
+    check_against_model(crate::SplitStrategy::Classic, 170);
+    check_against_model(crate::SplitStrategy::BStarDeferred, 171);
+}
+
+#[test]
+fn corrupted_size_is_detected() {
+    let mut tree = BTree::new(2);
+    for i in 0..200u32 {
+        tree.insert(i, i);
+    }
+    assert_eq!(tree.validate_sizes(), Ok(()));
+
+    tree.root.as_mut().unwrap().size += 1;
+    assert!(tree.validate_sizes().is_err());
+    tree.root.as_mut().unwrap().size -= 1;
+
+    // Deeper down, under a root whose own size is still right
+    let child = tree.root.as_mut().unwrap().child[1].as_mut().unwrap();
+    child.child[0].as_mut().unwrap().size -= 1;
+    assert!(tree.validate_sizes().is_err());
+}
+
+#[test]
+fn budget_below_the_height_is_exceeded() {
+    let mut tree = BTree::new(2);
+    for i in 0..1000u32 {
+        tree.insert(i * 2, i);
+    }
+    let height = tree.level_iter().count();
+    assert!(height > 3);
+
+    // Ruling out an absent key takes at least a comparison on every level
+    let absent = 501;
+    assert_eq!(
+        tree.search_budgeted(&absent, height - 1)
+            .map(|e| e.is_some()),
+        Err(crate::BudgetExceeded)
+    );
+    assert_eq!(
+        tree.search_budgeted(&absent, 0).map(|e| e.is_some()),
+        Err(crate::BudgetExceeded)
+    );
+
+    // A binary search per node stays well within log2 of the length, plus one per level
+    for k in 0..2000u32 {
+        let found = tree.search_budgeted(&k, 10 + height).unwrap();
+        assert_eq!(found.map(|e| e.value), tree.get(&k).copied());
+    }
+}
+
+#[test]
+fn entry_accessors_read_and_mutate_values() {
+    let mut tree = BTree::new(2);
+    for i in 0..50u32 {
+        tree.insert(i, i * 10);
+    }
+
+    let values: Vec<u32> = tree.range(10..15).map(|e| *e.value()).collect();
+    assert_eq!(values, [100, 110, 120, 130, 140]);
+
+    let e = tree.root.as_mut().unwrap().find_mut(&20).unwrap();
+    *e.value_mut() += 5;
+    assert_eq!(e.get_key(), &20);
+    assert_eq!(tree.get(&20), Some(&205));
+
+    let pairs: Vec<(u32, u32)> = tree.range(19..22).map(|e| e.into_pair()).collect();
+    assert_eq!(pairs, [(19, 190), (20, 205), (21, 210)]);
+}
+
+#[test]
+fn op_stats_count_the_workload() {
+    let mut tree = BTree::with_stats(2);
+    for i in 0..100u32 {
+        tree.insert(i, i);
+    }
+
+    // Every split adds one node, and each time the root split it also added a new root
+    let stats = tree.op_stats();
+    let nodes: usize = tree.level_iter().map(|level| level.len()).sum();
+    let height = tree.level_iter().count();
+    assert_eq!(stats.inserts, 100);
+    assert_eq!(stats.splits as usize, nodes - 1 - (height - 1));
+    assert_eq!((stats.removes, stats.searches, stats.merges), (0, 0, 0));
+
+    for i in 0..50u32 {
+        assert_eq!(tree.get(&(i * 3)).is_some(), i * 3 < 100);
+    }
+    for i in 0..20u32 {
+        tree.remove(&(i * 7));
+    }
+
+    let stats = tree.op_stats();
+    assert_eq!(stats.inserts, 100);
+    assert_eq!(stats.searches, 50);
+    // Removes are counted whether or not the key was there
+    assert_eq!(stats.removes, 20);
+
+    for i in 0..100u32 {
+        tree.remove(&i);
+    }
+    let stats = tree.op_stats();
+    assert!(stats.merges > 0);
+    assert!(tree.is_empty());
+
+    // A tree without stats reports zeros
+    let mut plain = BTree::new(2);
+    plain.insert(1, 1);
+    assert_eq!(plain.op_stats(), crate::OpStats::default());
+}
+
+// A tree can be shared between threads, whichever features are enabled
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<BTree<u64, u64>>();
+};
+
+#[test]
+fn op_stats_count_lookups_from_several_threads() {
+    let mut tree = BTree::with_stats(3);
+    for i in 0..1000u64 {
+        tree.insert(i, i);
+    }
+
+    let tree = &tree;
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(move || {
+                for i in 0..1000u64 {
+                    assert_eq!(tree.get(&i), Some(&i));
+                }
+            });
+        }
+    });
+    assert_eq!(tree.op_stats().searches, 4000);
+}
+
+#[test]
+fn get_cow_borrows_until_owned() {
+    use std::borrow::Cow;
+
+    let mut tree = BTree::new(2);
+    for i in 0..50u32 {
+        tree.insert(i, [i; 8]);
+    }
+
+    let value = tree.get_cow(&20).unwrap();
+    assert!(matches!(value, Cow::Borrowed(v) if std::ptr::eq(v, tree.get(&20).unwrap())));
+    assert_eq!(value.into_owned(), [20; 8]);
+    assert!(tree.get_cow(&50).is_none());
+}
+
+#[test]
+fn update_matching_doubles_the_selected_values() {
+    let mut tree = BTree::new(2);
+    for i in 0..500u32 {
+        tree.insert(i, i);
+    }
+
+    assert_eq!(tree.update_matching(|k| k % 2 == 0, |v| *v *= 2), 250);
+
+    for e in tree.iter() {
+        let expected = if e.key % 2 == 0 { e.key * 2 } else { e.key };
+        assert_eq!(e.value, expected);
+    }
+    assert_eq!(tree.update_matching(|k| *k >= 500, |v| *v = 0), 0);
+}
+
+#[cfg(feature = "smallvec")]
+#[test]
+fn small_nodes_keep_their_keys_inline() {
+    use crate::Node;
+
+    // At degree 2 only the child slots are on the heap, from degree 3 the keys spill over as well
+    let before = allocations();
+    let small: Node<u32, u32> = Node::new(2, true);
+    assert_eq!(allocations() - before, 1);
+    assert!(!small.keys.spilled());
+
+    let before = allocations();
+    let large: Node<u32, u32> = Node::new(3, true);
+    assert_eq!(allocations() - before, 2);
+    assert!(large.keys.spilled());
+
+    let mut tree = BTree::new(2);
+    for i in (0..1000u32).rev() {
+        tree.insert(i, i + 1);
+    }
+    assert_valid(&tree);
+    assert!((0..1000).all(|i| tree.get(&i) == Some(&(i + 1))));
+    assert_eq!(tree.get(&1000), None);
+}
+
+#[test]
+fn insert_ranked_returns_the_position_at_insert_time() {
+    let mut tree = BTree::new(3);
+    let mut model = Vec::new();
+    for i in (0..2000u32).step_by(2) {
+        assert_eq!(tree.insert_ranked(i, i), model.len());
+        model.push(i);
+    }
+
+    // Odd keys, and duplicates of even ones, in random order. A duplicate goes after the keys equal to it.
+    let mut seed = 185;
+    for _ in 0..1000 {
+        let key = (lcg(&mut seed) % 2000) as u32;
+        let expected = model.partition_point(|&k| k <= key);
+        assert_eq!(tree.insert_ranked(key, key), expected);
+        model.insert(expected, key);
+    }
+    assert_eq!(keys(&tree), model);
+    tree.validate_sizes().unwrap();
+}
+
+#[test]
+fn rebalance_fixes_the_nodes_lazy_removals_left_underfull() {
+    let mut tree = BTree::new(4);
+    for i in 0..5000u32 {
+        tree.insert(i, i);
+    }
+    let underfull = |tree: &BTree<u32, u32>| {
+        tree.level_iter()
+            .skip(1)
+            .flatten()
+            .filter(|node| node.n < tree.t - 1)
+            .count()
+    };
+
+    // Remove four in five keys, in random order
+    let mut seed = 187;
+    let mut removed: Vec<u32> = (0..5000).filter(|k| k % 5 != 0).collect();
+    for i in (1..removed.len()).rev() {
+        removed.swap(i, lcg(&mut seed) as usize % (i + 1));
+    }
+    for k in removed.iter() {
+        assert_eq!(tree.remove_lazy(k).map(|e| e.key), Some(*k));
+    }
+    assert!(tree.remove_lazy(&1).is_none());
+
+    // The tree is still searchable, just not filled up
+    let expected: Vec<u32> = (0..5000).step_by(5).collect();
+    assert!(tree.is_balanced());
+    tree.validate_sizes().unwrap();
+    tree.assert_key_order();
+    assert_eq!(keys(&tree), expected);
+    assert!(tree.level_iter().flatten().all(|node| node.n > 0));
+    let before = underfull(&tree);
+    assert!(before > 0);
+
+    tree.rebalance();
+
+    assert_valid(&tree);
+    assert_eq!(keys(&tree), expected);
+    assert!(underfull(&tree) < before);
+    assert_eq!(underfull(&tree), 0);
+
+    // Lazily removing everything leaves an empty tree
+    for k in expected.iter() {
+        tree.remove_lazy(k);
+    }
+    assert!(tree.is_empty());
+    assert_eq!((tree.min(), tree.max()), (None, None));
+}
+
+#[test]
+fn insert_ref_points_at_the_stored_value() {
+    let mut tree = BTree::new(2);
+    for i in 0..1000u32 {
+        // Ascending inserts keep splitting the right-most nodes, moving the new entries around
+        *tree.insert_ref(i, 0) += i + 1;
+    }
+    assert_valid(&tree);
+    assert!((0..1000).all(|i| tree.get(&i) == Some(&(i + 1))));
+
+    // A duplicate gets its own reference, the existing entry is left alone
+    *tree.insert_ref(500, 7) *= 2;
+    let values: Vec<u32> = tree.range(500..=500).map(|e| e.value).collect();
+    assert_eq!(values, vec![501, 14]);
+}
+
+#[test]
+fn find_stops_at_the_first_match() {
+    use std::cell::Cell;
+
+    let mut tree = BTree::new(3);
+    for i in 0..1000u32 {
+        tree.insert(i, (i * 7) % 100);
+    }
+
+    // The first key whose value is above 90 is 13, with 91
+    let calls = Cell::new(0);
+    let found = tree.find(|_, v| {
+        calls.set(calls.get() + 1);
+        *v > 90
+    });
+    assert_eq!(found.map(|e| (e.key, e.value)), Some((13, 91)));
+    assert_eq!(calls.get(), 14);
+
+    assert!(tree.find(|_, v| *v >= 100).is_none());
+}
+
+#[test]
+fn parts_round_trip() {
+    let mut tree = BTree::new(3);
+    let mut seed = 195;
+    for _ in 0..2000 {
+        let key = lcg(&mut seed) as u32;
+        tree.insert(key, key / 2);
+    }
+    let expected: Vec<(u32, u32)> = tree.iter().map(|e| (e.key, e.value)).collect();
+
+    let (root, t) = tree.into_parts();
+    assert_eq!(t, 3);
+    assert_eq!(root.as_ref().map(|r| r.size), Some(2000));
+
+    let tree = BTree::from_parts(root, t);
+    assert_valid(&tree);
+    assert_eq!(
+        tree.iter().map(|e| (e.key, e.value)).collect::<Vec<_>>(),
+        expected
+    );
+    assert_eq!(tree.min(), expected.first().map(|e| &e.0));
+    assert_eq!(tree.max(), expected.last().map(|e| &e.0));
+
+    let (root, t) = BTree::<u32, u32>::new(2).into_parts();
+    assert!(root.is_none());
+    assert!(BTree::from_parts(root, t).is_empty());
+}
+
+#[test]
+fn validate_values_reports_the_first_offender() {
+    let mut tree = BTree::new(2);
+    for i in 0..500i32 {
+        tree.insert(i, i - 10 * (i / 10));
+    }
+    assert_eq!(tree.validate_values(|_, v| *v >= 0), Ok(()));
+
+    tree.insert(1000, -1);
+    *tree.get_mut(&300).unwrap() = -5;
+    assert_eq!(tree.validate_values(|_, v| *v >= 0), Err(300));
+    assert_eq!(tree.validate_values(|k, v| *k < 400 || *v >= 0), Err(1000));
+}
+
+#[test]
+fn insert_before_split_check_updates_without_splitting() {
+    let mut tree = BTree::with_stats(2);
+    for i in 0..1000u32 {
+        assert!(tree.insert_before_split_check(i, i));
+    }
+    let splits = tree.op_stats().splits;
+    let nodes = tree.level_iter().flatten().count();
+
+    for i in 0..1000u32 {
+        assert!(!tree.insert_before_split_check(i, i * 3));
+    }
+
+    assert_eq!(tree.op_stats().splits, splits);
+    assert_eq!(tree.level_iter().flatten().count(), nodes);
+    assert_valid(&tree);
+    assert!(tree.iter().all(|e| e.value == e.key * 3));
+}
+
+#[test]
+fn insert_before_split_check_merges_updates() {
+    let mut tree = BTree::with_merge_fn(2, |old: u32, new| old + new);
+    for i in 0..100u32 {
+        assert!(tree.insert_before_split_check(i, 1));
+    }
+    for i in 0..100u32 {
+        assert!(!tree.insert_before_split_check(i, i));
+    }
+
+    // Updates went through the merge function, like they do through insert
+    assert_eq!(tree.len(), 100);
+    assert!(tree.iter().all(|e| e.value == e.key + 1));
+    tree.insert(7, 10);
+    assert_eq!(tree.get(&7), Some(&18));
+    assert_valid(&tree);
+}
+
+#[test]
+fn merge_fn_keeps_the_larger_value() {
+    let mut tree = BTree::with_merge_fn(2, |old: u32, new| old.max(new));
+    for i in 0..100u32 {
+        tree.insert(i, 50);
+    }
+
+    tree.insert(10, 20);
+    tree.insert(20, 80);
+    tree.insert_ranked(30, 90);
+
+    assert_eq!(tree.len(), 100);
+    assert_eq!(tree.get(&10), Some(&50));
+    assert_eq!(tree.get(&20), Some(&80));
+    assert_eq!(tree.get(&30), Some(&90));
+    assert_valid(&tree);
+
+    // A merge function may capture shared state, as long as it's Send + Sync
+    let merges = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counter = std::sync::Arc::clone(&merges);
+    let mut tree = BTree::with_merge_fn(2, move |old: u32, new| {
+        counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        old.max(new)
+    });
+    for i in 0..100u32 {
+        tree.insert(i % 40, i);
+    }
+    assert_eq!(merges.load(std::sync::atomic::Ordering::Relaxed), 60);
+    assert_eq!(tree.get(&5), Some(&85));
+}
+
+#[test]
+fn split_observer_sees_every_separator() {
+    use std::sync::{Arc, Mutex};
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut tree = BTree::with_stats(2);
+    let sink = Arc::clone(&seen);
+    tree.observe_splits(move |k: &u32| sink.lock().unwrap().push(*k));
+    for i in 0..200u32 {
+        tree.insert(i, i);
+    }
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len() as u64, tree.op_stats().splits);
+    // Every separator was promoted into an internal node, and sequential inserts never demote one
+    let internal: Vec<u32> = tree
+        .level_iter()
+        .flatten()
+        .filter(|n| !n.leaf)
+        .flat_map(|n| n.iter_keys().copied())
+        .collect();
+    assert!(seen.iter().all(|k| internal.contains(k)));
+}
+
+#[test]
+fn bracket_finds_the_floor_and_the_ceiling() {
+    let empty: BTree<u32, u32> = BTree::new(2);
+    let (floor, ceiling) = empty.bracket(&5);
+    assert!(floor.is_none() && ceiling.is_none());
+
+    let mut tree = BTree::new(2);
+    for i in 1..100u32 {
+        tree.insert(i * 10, i);
+    }
+    let keys_of = |(floor, ceiling): crate::Bracket<'_, u32, u32>| {
+        (floor.map(|e| e.key), ceiling.map(|e| e.key))
+    };
+
+    // A present key is both its own floor and ceiling
+    assert_eq!(keys_of(tree.bracket(&500)), (Some(500), Some(500)));
+    assert_eq!(keys_of(tree.bracket(&10)), (Some(10), Some(10)));
+    assert_eq!(keys_of(tree.bracket(&990)), (Some(990), Some(990)));
+    // A key between two entries
+    assert_eq!(keys_of(tree.bracket(&505)), (Some(500), Some(510)));
+    // Below the minimum and above the maximum
+    assert_eq!(keys_of(tree.bracket(&3)), (None, Some(10)));
+    assert_eq!(keys_of(tree.bracket(&1000)), (Some(990), None));
+
+    // Every key agrees with a scan, wherever its neighbours sit in the tree
+    for key in 0..1005u32 {
+        let floor = tree.iter().map(|e| e.key).rev().find(|k| *k <= key);
+        let ceiling = tree.iter().map(|e| e.key).find(|k| *k >= key);
+        assert_eq!(keys_of(tree.bracket(&key)), (floor, ceiling), "key {}", key);
+    }
+    assert_eq!(tree.bracket(&505).0.map(|e| e.value), Some(50));
+}
+
+#[test]
+fn compare_and_swap_only_replaces_the_expected_value() {
+    let mut tree = BTree::new(2);
+    for i in 0..100u32 {
+        tree.insert(i, i * 2);
+    }
+
+    assert_eq!(tree.compare_and_swap(&40, &80, 7), Ok(()));
+    assert_eq!(tree.get(&40), Some(&7));
+
+    // A stale expectation hands back the current value and leaves it in place
+    assert_eq!(tree.compare_and_swap(&40, &80, 9), Err(Some(7)));
+    assert_eq!(tree.get(&40), Some(&7));
+    assert_eq!(tree.compare_and_swap(&41, &0, 9), Err(Some(82)));
+    assert_eq!(tree.get(&41), Some(&82));
+
+    assert_eq!(tree.compare_and_swap(&500, &0, 9), Err(None));
+    assert_eq!(tree.get(&500), None);
+    assert_eq!(tree.len(), 100);
+
+    let mut empty: BTree<u32, u32> = BTree::new(2);
+    assert_eq!(empty.compare_and_swap(&1, &1, 2), Err(None));
+    assert!(empty.is_empty());
+}
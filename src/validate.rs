@@ -0,0 +1,132 @@
+use crate::{BTree, Node, VerifyError};
+
+impl<K, P> Node<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    // Recomputes the size of this subtree bottom-up, failing at the first node whose stored size disagrees.
+    // path lists the child indexes leading here from the root.
+    fn checked_size(&self, path: &mut Vec<usize>) -> Result<usize, String> {
+        let mut size = self.n;
+        if !self.leaf {
+            for i in 0..=self.n {
+                path.push(i);
+                size += self.child[i].as_ref().unwrap().checked_size(path)?;
+                path.pop();
+            }
+        }
+
+        if size != self.size {
+            return Err(format!(
+                "Node at child path {:?} stores size {} but holds {} entries",
+                path, self.size, size
+            ));
+        }
+        Ok(size)
+    }
+
+    // Returns the depth of the leaves below this node, or None if they aren't all at the same depth
+    fn leaf_depth(&self) -> Option<usize> {
+        if self.leaf {
+            return Some(0);
+        }
+
+        let depth = self.child[0].as_ref().unwrap().leaf_depth()?;
+        for i in 1..=self.n {
+            if self.child[i].as_ref().unwrap().leaf_depth()? != depth {
+                return None;
+            }
+        }
+        Some(depth + 1)
+    }
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Compares the entries of the tree, in key order, against a sorted reference slice
+    /// and reports the first difference
+    pub fn verify_against_slice(&self, expected: &[(K, P)]) -> Result<(), VerifyError<K, P>>
+    where
+        P: PartialEq,
+    {
+        if self.len() != expected.len() {
+            return Err(VerifyError::LengthMismatch {
+                expected: expected.len(),
+                found: self.len(),
+            });
+        }
+
+        for (index, (e, &(key, value))) in self.iter().zip(expected.iter()).enumerate() {
+            if e.key != key {
+                return Err(VerifyError::KeyMismatch {
+                    index,
+                    expected: key,
+                    found: e.key,
+                });
+            }
+            if e.value != value {
+                return Err(VerifyError::ValueMismatch {
+                    index,
+                    expected: value,
+                    found: e.value,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks a domain invariant on every entry, e.g. that all values are non-negative,
+    /// and returns the first key, in key order, whose entry breaks it
+    pub fn validate_values<F>(&self, pred: F) -> Result<(), K>
+    where
+        F: Fn(&K, &P) -> bool,
+    {
+        match self.find(|k, v| !pred(k, v)) {
+            Some(e) => Err(e.key),
+            None => Ok(()),
+        }
+    }
+
+    /// Panics if the keys, in traversal order, aren't strictly increasing.
+    /// Only checks the sort order, not the shape of the nodes.
+    pub fn assert_key_order(&self) {
+        let mut iter = self.iter();
+        let mut prev = match iter.next() {
+            Some(e) => e.key,
+            None => return,
+        };
+
+        for (i, e) in iter.enumerate() {
+            if e.key <= prev {
+                panic!(
+                    "Key at position {} is not greater than the key before it",
+                    i + 1
+                );
+            }
+            prev = e.key;
+        }
+    }
+
+    /// Checks that every leaf is at the same depth, visiting every node.
+    /// A sanity check to run after restructuring the tree, e.g. with `merge` or `retain_range`.
+    pub fn is_balanced(&self) -> bool {
+        match &self.root {
+            Some(r) => r.leaf_depth().is_some(),
+            None => true,
+        }
+    }
+
+    /// Recomputes the subtree size of every node and compares it to the stored size that
+    /// `select`, `rank` and the range counts rely on, reporting the first mismatch
+    pub fn validate_sizes(&self) -> Result<(), String> {
+        match &self.root {
+            Some(r) => r.checked_size(&mut Vec::new()).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+}
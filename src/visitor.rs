@@ -0,0 +1,44 @@
+use crate::{BTree, Node};
+
+/// Callbacks for walking the nodes of a `BTree` with `BTree::accept`, every one of them does nothing by default
+pub trait Visitor<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    fn visit_internal(&mut self, _node: &Node<K, P>, _depth: usize) {}
+    fn visit_leaf(&mut self, _node: &Node<K, P>, _depth: usize) {}
+}
+
+impl<K, P> Node<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    // Hands this subtree to the visitor in pre-order
+    pub fn accept<V: Visitor<K, P>>(&self, visitor: &mut V, depth: usize) {
+        if self.leaf {
+            visitor.visit_leaf(self, depth);
+            return;
+        }
+
+        visitor.visit_internal(self, depth);
+        for i in 0..=self.n {
+            self.child[i].as_ref().unwrap().accept(visitor, depth + 1);
+        }
+    }
+}
+
+impl<K, P> BTree<K, P>
+where
+    K: Eq + Ord + Copy,
+    P: Copy,
+{
+    /// Walks the nodes in pre-order, calling `visit_internal` or `visit_leaf` on the visitor for each of them
+    /// along with its depth, where the root is at depth 0
+    pub fn accept<V: Visitor<K, P>>(&self, visitor: &mut V) {
+        if let Some(r) = &self.root {
+            r.accept(visitor, 0);
+        }
+    }
+}